@@ -0,0 +1,62 @@
+use super::{
+    perf::SCHED_CAPACITY_SCALE,
+    state::{CpuId, Ticks},
+    topology::Topology,
+};
+
+// Warm-cache model. A task's cache footprint is worth `cold_penalty` ticks of
+// extra service to rebuild from scratch. Moving away from the previous CPU
+// loses the part of it that isn't shared with the new one, and the rest
+// decays while the task isn't running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheModel {
+    pub cold_penalty: Ticks,
+    // Share of the footprint still usable at each distance, in
+    // SCHED_CAPACITY_SCALE units. Nothing survives a move to another node.
+    pub smt_warmth: u64,
+    pub llc_warmth: u64,
+    pub node_warmth: u64,
+    // Ticks for an unused footprint to lose half its worth
+    pub half_life: Ticks,
+}
+
+impl Default for CacheModel {
+    fn default() -> Self {
+        Self {
+            cold_penalty: 2,
+            smt_warmth: SCHED_CAPACITY_SCALE * 3 / 4,
+            llc_warmth: SCHED_CAPACITY_SCALE / 2,
+            node_warmth: SCHED_CAPACITY_SCALE / 8,
+            half_life: 16,
+        }
+    }
+}
+
+impl CacheModel {
+    fn warmth(&self, topology: &Topology, from: CpuId, to: CpuId) -> u64 {
+        if from == to {
+            SCHED_CAPACITY_SCALE
+        } else if topology.cpus[from].core == topology.cpus[to].core {
+            self.smt_warmth
+        } else if topology.share_llc(from, to) {
+            self.llc_warmth
+        } else if topology.share_node(from, to) {
+            self.node_warmth
+        } else {
+            0
+        }
+    }
+
+    // Extra work, in SCHED_CAPACITY_SCALE units, for a task that last ran on
+    // `from` `idle` ticks ago to start on `to`
+    pub fn penalty(&self, topology: &Topology, from: CpuId, to: CpuId, idle: Ticks) -> u64 {
+        let mut warmth = self.warmth(topology, from, to);
+        if let Some(halvings) = idle.checked_div(self.half_life) {
+            let rest = idle % self.half_life;
+            warmth = warmth.checked_shr(halvings as u32).unwrap_or(0);
+            // Linear between halvings
+            warmth -= warmth * rest / (2 * self.half_life);
+        }
+        self.cold_penalty * (SCHED_CAPACITY_SCALE - warmth)
+    }
+}
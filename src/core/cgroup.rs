@@ -0,0 +1,62 @@
+use slotmap::new_key_type;
+
+use super::state::Ticks;
+
+new_key_type! {
+    pub struct CgroupId;
+}
+
+pub const CGROUP_WEIGHT_DFL: u64 = 100;
+
+// cpu.max: at most `quota` ticks of CPU time per `period`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuMax {
+    pub quota: Ticks,
+    pub period: Ticks,
+}
+
+#[derive(Debug)]
+pub struct Cgroup {
+    pub id: CgroupId,
+    pub parent: Option<CgroupId>,
+    pub children: Vec<CgroupId>,
+    pub path: String,
+    // cpu.weight, Range: 1..10000
+    pub weight: u64,
+    pub max: Option<CpuMax>,
+    // CPU time consumed by this group and its descendants, in total and in the
+    // current cpu.max period
+    pub usage: Ticks,
+    pub period_usage: Ticks,
+    pub throttled: bool,
+}
+
+impl Cgroup {
+    pub(crate) fn new(id: CgroupId, parent: Option<CgroupId>, path: String) -> Self {
+        Self {
+            id,
+            parent,
+            children: Vec::new(),
+            path,
+            weight: CGROUP_WEIGHT_DFL,
+            max: None,
+            usage: 0,
+            period_usage: 0,
+            throttled: false,
+        }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.parent.is_none()
+    }
+}
+
+// Joins a child name onto a cgroup path, so that the root is "/" and its
+// children are "/name"
+pub(crate) fn child_path(parent: &str, name: &str) -> String {
+    if parent == "/" {
+        format!("/{name}")
+    } else {
+        format!("{parent}/{name}")
+    }
+}
@@ -0,0 +1,25 @@
+use super::state::Ticks;
+
+// Activity above sched_ext that can take a CPU away from SCX tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HigherClass {
+    Stop,
+    Deadline,
+    Rt,
+    Irq,
+    Softirq,
+}
+
+impl HigherClass {
+    // Stop/DL/RT tasks switch the CPU away from sched_ext (cpu_release), while
+    // IRQs and softirqs only stall whatever is running underneath them
+    pub fn releases_cpu(&self) -> bool {
+        matches!(self, Self::Stop | Self::Deadline | Self::Rt)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CpuSteal {
+    pub class: HigherClass,
+    pub until: Ticks,
+}
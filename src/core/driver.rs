@@ -1,12 +1,24 @@
+use std::cmp;
+
+use rustc_hash::FxHashMap;
+
 use super::{
+    cache::CacheModel,
+    cgroup::{self, CgroupId, CpuMax},
+    class::{CpuSteal, HigherClass},
+    idle::IdleState,
+    lock::{Lock, LockId, LockKind},
     observer::Observer,
-    state::{CpuId, KernelCtx, TaskId, Ticks},
+    order::{CpuOrder, CpuOrderGen},
+    overhead::{Callback, OverheadModel},
+    perf::{Governor, SCHED_CAPACITY_SCALE},
+    state::{CpuActivity, CpuId, DsqId, KernelCtx, TaskAttrs, TaskId, Ticks},
 };
 use crate::{
-    core::{TaskState, event::SchedCoreEvent},
+    core::{BlockReason, TaskState, event::SchedCoreEvent},
     scheduler::{
-        EnqueueFlags, SCX_ENQ_CPU_SELECTED, SCX_ENQ_REENQ, SCX_ENQ_WAKEUP, Scheduler,
-        SelectCpuDecision,
+        EnqueueFlags, SCX_ENQ_CPU_SELECTED, SCX_ENQ_REENQ, SCX_ENQ_WAKEUP, SCX_KICK_PREEMPT,
+        SCX_SLICE_DFL, SCX_WAKE_TTWU, Scheduler, SelectCpuDecision, WakeFlags,
     },
 };
 
@@ -15,93 +27,659 @@ pub struct SchedCore<S: Scheduler> {
     pub scheduler: S,
     observer: Observer,
     events: Vec<SchedCoreEvent>,
+    // Runnable tasks held back by cpu.max throttling, with the CPU they last left
+    throttled_tasks: Vec<(TaskId, CpuId)>,
+    // Set once the scheduler has been ejected
+    exit_reason: Option<String>,
+    activity: Vec<CpuActivity>,
+    cpu_order: CpuOrderGen,
 }
 
 impl<S: Scheduler> SchedCore<S> {
     pub fn new(num_cpus: usize) -> Self {
-        let mut ctx = KernelCtx::new(num_cpus);
-        let scheduler = S::init(&mut ctx);
+        Self::with_scheduler(num_cpus, S::init)
+    }
+
+    // For schedulers that take configuration beyond what Scheduler::init provides
+    pub fn with_scheduler(num_cpus: usize, init: impl FnOnce(&mut KernelCtx) -> S) -> Self {
+        Self::with_ctx(KernelCtx::new(num_cpus), init)
+    }
+
+    // For other machine shapes, e.g. KernelCtx::with_topology()
+    pub fn with_ctx(mut ctx: KernelCtx, init: impl FnOnce(&mut KernelCtx) -> S) -> Self {
+        let scheduler = init(&mut ctx);
         let observer = Observer::new();
         Self {
             ctx,
             scheduler,
             observer,
             events: Vec::new(),
+            throttled_tasks: Vec::new(),
+            exit_reason: None,
+            activity: Vec::new(),
+            cpu_order: CpuOrderGen::new(CpuOrder::default()),
         }
     }
 
     pub fn tick(&mut self) -> Vec<SchedCoreEvent> {
-        for cpu in 0..self.ctx.cpus.len() {
+        self.tick_timed()
+            .into_iter()
+            .map(|(_, event)| event)
+            .collect()
+    }
+
+    // Events paired with when they happened: the start of the tick, or its end
+    // for everything from tasks using up their service onwards
+    pub fn tick_timed(&mut self) -> Vec<(Ticks, SchedCoreEvent)> {
+        self.refresh_cgroup_bandwidth();
+        self.handle_kicks();
+        self.end_expired_steals();
+        self.check_offline_dispatches();
+        let order = self.cpu_order.next(self.ctx.cpus.len(), self.ctx.now);
+        self.ctx.take_dsq_pops();
+        let mut contenders: FxHashMap<DsqId, u64> = FxHashMap::default();
+        for &cpu in &order {
             self.schedule_cpu(cpu);
+            self.charge_contention(cpu, &mut contenders);
         }
-        for cpu in 0..self.ctx.cpus.len() {
+        // Sampled before tick_cpu() can take tasks off their CPU
+        self.activity = (0..self.ctx.cpus.len())
+            .map(|cpu| self.ctx.cpu_activity(cpu))
+            .collect();
+        let tick_end = self.events.len();
+        for &cpu in &order {
             self.tick_cpu(cpu);
         }
+        self.check_offline_dispatches();
+        for (cpu, from, to) in self.ctx.update_cpufreq(&self.activity) {
+            self.events
+                .push(SchedCoreEvent::CpuPerfChange { cpu, from, to });
+        }
         self.observer.observe(&self.ctx);
 
+        let now = self.ctx.now;
         self.ctx.advance_time(1);
         std::mem::take(&mut self.events)
+            .into_iter()
+            .enumerate()
+            .map(|(i, event)| (if i < tick_end { now } else { now + 1 }, event))
+            .collect()
     }
 
     fn tick_cpu(&mut self, cpu: CpuId) {
+        // Whatever runs underneath a higher class makes no progress
+        if !self.ctx.cpus[cpu].online
+            || self.ctx.cpus[cpu].stolen.is_some()
+            || self.ctx.cpu_is_waking(cpu)
+        {
+            return;
+        }
         let Some(current_task_id) = self.ctx.cpus[cpu].current else {
             self.events.push(SchedCoreEvent::CpuIdle { cpu });
+            self.ctx.pay_overhead(cpu);
+            if let Some(state) = self.ctx.update_idle_state(cpu) {
+                self.events
+                    .push(SchedCoreEvent::CpuIdleEnter { cpu, state });
+            }
             return;
         };
 
         // Increment service/decrement slice for current task
         // THIS IS THE ACTUAL "TIME BETWEEN TICKS"
-        // In its own block to avoid double-mutable-borrow
+        // Slices are wall-clock time, but service depends on capacity, frequency,
+        // SMT contention and whatever overhead the CPU still has to pay off
+        let time = self.ctx.pay_overhead(cpu);
+        // A spinning lock waiter burns its time until the lock is handed to it
+        if self.ctx.task(current_task_id).lock_waiting.is_none() {
+            let work = self.ctx.cpu_throughput(cpu, &self.activity) * time / SCHED_CAPACITY_SCALE;
+            self.ctx.charge_service(current_task_id, work);
+        }
         {
             let task = self.ctx.task_mut(current_task_id);
-            task.consumed_service = task.consumed_service.saturating_add(1);
             task.consumed_timeslice = task.consumed_timeslice.saturating_add(1);
         }
+        let cgroup = self.ctx.task(current_task_id).cgroup;
+        for cgroup in self.ctx.charge_cgroup(cgroup, 1) {
+            self.events.push(SchedCoreEvent::CgroupThrottled { cgroup });
+        }
 
         // The following occurs "at the end" of the tick
 
         // Invoke BPF ops->tick()
-        self.scheduler.tick(&mut self.ctx, current_task_id);
+        self.call(cpu, Callback::Tick, |ops, ctx| {
+            ops.tick(ctx, current_task_id)
+        });
+
+        let lock_blocked = self.update_locks(current_task_id);
 
         let task = self.ctx.task_mut(current_task_id);
         let completed = task.consumed_service >= task.required_service;
+        // A task waiting for a lock has yet to get as far as its block point
+        let blocked = !completed
+            && task.lock_waiting.is_none()
+            && task.block_at.is_some_and(|at| task.consumed_service >= at);
         let slice_expired = task.consumed_timeslice
-            == task
+            >= task
                 .allocated_timeslice
                 .expect("Task has an unset timeslice")
             && !completed;
 
-        if !completed && !slice_expired {
+        let throttled = self.ctx.task_is_throttled(current_task_id);
+
+        let (to, reason) = if completed {
+            (TaskState::Completed, None)
+        } else if lock_blocked {
+            let (lock, _) = self
+                .ctx
+                .task(current_task_id)
+                .lock_waiting
+                .expect("Lock-blocked task is not waiting");
+            (TaskState::Blocked, Some(BlockReason::Lock(lock)))
+        } else if blocked {
+            self.ctx.task_mut(current_task_id).block_at = None;
+            (TaskState::Blocked, Some(BlockReason::BlockAt))
+        } else if slice_expired || throttled {
+            (TaskState::Runnable, None)
+        } else {
             return;
+        };
+
+        self.deschedule(cpu, current_task_id, to, reason);
+    }
+
+    // Common "deschedule" path, for completion, sleeping, slice expiry and preemption
+    fn deschedule(&mut self, cpu: CpuId, task: TaskId, to: TaskState, reason: Option<BlockReason>) {
+        self.put_prev(cpu, task, to, reason);
+        if to == TaskState::Runnable {
+            self.requeue(task, cpu);
         }
+    }
 
-        // Common "deschedule" path
-        self.scheduler
-            .stopping(&mut self.ctx, current_task_id, !completed);
+    // Take `task` off `cpu`, leaving it in state `to` but not queued anywhere
+    fn put_prev(&mut self, cpu: CpuId, task: TaskId, to: TaskState, reason: Option<BlockReason>) {
+        let runnable = to == TaskState::Runnable;
+        if runnable {
+            self.check_holder_preempted(task);
+        }
+        self.call(cpu, Callback::Stopping, |ops, ctx| {
+            ops.stopping(ctx, task, runnable)
+        });
         self.ctx.clear_cpu(cpu);
         self.events.push(SchedCoreEvent::CpuCurrentChange {
             cpu,
-            from: Some(current_task_id),
+            from: Some(task),
             to: None,
         });
 
-        if completed {
-            self.ctx.mark_completed(current_task_id, self.ctx.now);
-            self.events.push(SchedCoreEvent::TaskStateChange {
-                task: current_task_id,
-                from: TaskState::Running,
-                to: TaskState::Completed,
+        if !runnable {
+            self.call(cpu, Callback::Quiescent, |ops, ctx| {
+                ops.quiescent(ctx, task)
+            });
+        }
+        match to {
+            TaskState::Runnable => self.ctx.mark_runnable(task),
+            TaskState::Blocked => self.ctx.mark_blocked(task),
+            TaskState::Completed => self.ctx.mark_completed(task, self.ctx.now),
+            TaskState::Running => unreachable!("Task {task} is leaving its CPU"),
+        }
+        self.events.push(SchedCoreEvent::TaskStateChange {
+            task,
+            from: TaskState::Running,
+            to,
+            reason,
+        });
+    }
+
+    // Enters and leaves the critical sections `task` has reached.
+    // Returns whether it must sleep on a contended lock.
+    fn update_locks(&mut self, task_id: TaskId) -> bool {
+        loop {
+            let task = self.ctx.task(task_id);
+            let Some(&section) = task.critical_sections.front() else {
+                return false;
+            };
+            // Nothing past a block point happens until the task is woken from it
+            let progress = match task.block_at {
+                Some(at) => task.consumed_service.min(at),
+                None => task.consumed_service,
+            };
+            if task.lock_held == Some(section.lock) {
+                if progress < section.start + section.len {
+                    return false;
+                }
+                self.ctx.task_mut(task_id).critical_sections.pop_front();
+                self.release_lock(task_id, section.lock);
+                continue;
+            }
+            if task.lock_waiting.is_some() || progress < section.start {
+                return false;
+            }
+            if !self.acquire_lock(task_id, section.lock) {
+                return self.ctx.locks[section.lock].kind == LockKind::Sleep;
+            }
+        }
+    }
+
+    // Takes `lock` if it is free, or queues `task` on it
+    fn acquire_lock(&mut self, task: TaskId, lock_id: LockId) -> bool {
+        let now = self.ctx.now;
+        let lock = &mut self.ctx.locks[lock_id];
+        let Some(holder) = lock.holder else {
+            lock.holder = Some(task);
+            self.ctx.task_mut(task).lock_held = Some(lock_id);
+            return true;
+        };
+        lock.waiters.push_back(task);
+        self.ctx.task_mut(task).lock_waiting = Some((lock_id, now));
+        self.events.push(SchedCoreEvent::LockContended {
+            task,
+            lock: lock_id,
+            holder,
+        });
+        self.inherit_priority(lock_id);
+        false
+    }
+
+    // Hands `lock` to its first waiter, if any
+    fn release_lock(&mut self, task: TaskId, lock_id: LockId) {
+        let now = self.ctx.now;
+        let lock = &mut self.ctx.locks[lock_id];
+        debug_assert_eq!(
+            lock.holder,
+            Some(task),
+            "Task {task} does not hold lock {lock_id}"
+        );
+        let holder_weight = lock.holder_weight.take();
+        let next = lock.waiters.pop_front();
+        let kind = lock.kind;
+        lock.holder = next;
+
+        self.ctx.task_mut(task).lock_held = None;
+        if let Some(weight) = holder_weight {
+            self.set_task_weight(task, weight);
+        }
+        self.events.push(SchedCoreEvent::LockReleased {
+            task,
+            lock: lock_id,
+        });
+
+        let Some(next) = next else {
+            return;
+        };
+        let waiter = self.ctx.task_mut(next);
+        let (_, since) = waiter
+            .lock_waiting
+            .take()
+            .expect("Lock waiter is not waiting");
+        waiter.lock_held = Some(lock_id);
+        waiter.lock_wait += now - since;
+        self.events.push(SchedCoreEvent::LockAcquired {
+            task: next,
+            lock: lock_id,
+            waited: now - since,
+        });
+        self.inherit_priority(lock_id);
+        if kind == LockKind::Sleep {
+            self.wake_lock_waiter(next, task);
+        }
+    }
+
+    // A sleeping waiter that was handed a lock runs again, unless it had already
+    // passed its block point when it went to sleep: then it stays asleep, now at
+    // that point or on the next lock before it
+    fn wake_lock_waiter(&mut self, task: TaskId, waker: TaskId) {
+        let state = self.ctx.task(task);
+        if state.block_at.is_none_or(|at| state.consumed_service < at) {
+            self.wake_task_by(task, waker, SCX_WAKE_TTWU);
+            return;
+        }
+
+        self.update_locks(task);
+        if let Some((lock, _)) = self.ctx.task(task).lock_waiting {
+            // Spinning happens on a CPU
+            if self.ctx.locks[lock].kind == LockKind::Spin {
+                self.wake_task_by(task, waker, SCX_WAKE_TTWU);
+            }
+            return;
+        }
+        self.ctx.task_mut(task).block_at = None;
+        self.events.push(SchedCoreEvent::TaskStateChange {
+            task,
+            from: TaskState::Blocked,
+            to: TaskState::Blocked,
+            reason: Some(BlockReason::BlockAt),
+        });
+    }
+
+    // With priority inheritance, the holder runs at the weight of its heaviest waiter
+    fn inherit_priority(&mut self, lock_id: LockId) {
+        let lock = &self.ctx.locks[lock_id];
+        let Some(holder) = lock.holder else {
+            return;
+        };
+        if !lock.priority_inheritance {
+            return;
+        }
+        let heaviest = lock
+            .waiters
+            .iter()
+            .map(|&waiter| self.ctx.task(waiter).weight)
+            .max()
+            .unwrap_or(0);
+        if heaviest <= self.ctx.task(holder).weight {
+            return;
+        }
+        let weight = self.ctx.task(holder).weight;
+        self.ctx.locks[lock_id].holder_weight.get_or_insert(weight);
+        self.set_task_weight(holder, heaviest);
+    }
+
+    fn check_holder_preempted(&mut self, task: TaskId) {
+        let Some(lock_id) = self.ctx.task(task).lock_held else {
+            return;
+        };
+        let lock = &self.ctx.locks[lock_id];
+        let weight = lock.holder_weight.unwrap_or(self.ctx.task(task).weight);
+        if let Some(&waiter) = lock
+            .waiters
+            .iter()
+            .find(|&&waiter| self.ctx.task(waiter).weight > weight)
+        {
+            self.events.push(SchedCoreEvent::LockHolderPreempted {
+                task,
+                lock: lock_id,
+                waiter,
             });
+        }
+    }
+
+    // Hand a runnable task that left `cpu` back to the scheduler, unless its cgroup is throttled
+    fn requeue(&mut self, task: TaskId, cpu: CpuId) {
+        if self.ctx.task_is_throttled(task) {
+            self.throttled_tasks.push((task, cpu));
         } else {
-            // Slice expired
-            self.ctx.mark_runnable(current_task_id);
-            self.events.push(SchedCoreEvent::TaskStateChange {
-                task: current_task_id,
-                from: TaskState::Running,
-                to: TaskState::Runnable,
+            self.enqueue(task, SCX_ENQ_REENQ, cpu);
+        }
+    }
+
+    fn enqueue(&mut self, task: TaskId, flags: EnqueueFlags, cpu: CpuId) {
+        let enqueued = self.call(cpu, Callback::Enqueue, |ops, ctx| {
+            ops.enqueue(ctx, task, flags, cpu)
+        });
+        if enqueued.is_none() {
+            let dsq = self.ctx.global_dsq();
+            self.ctx.dsq_push_fifo(dsq, task, SCX_SLICE_DFL);
+        }
+    }
+
+    // Every CPU that popped from a shared DSQ waits for those that got to it
+    // earlier in the tick
+    fn charge_contention(&mut self, cpu: CpuId, contenders: &mut FxHashMap<DsqId, u64>) {
+        let mut pops = self.ctx.take_dsq_pops();
+        pops.sort();
+        pops.dedup();
+        for dsq in pops {
+            if self.ctx.per_cpu_dsq_ids.contains(&dsq) {
+                continue;
+            }
+            let earlier = contenders.entry(dsq).or_default();
+            let cost = self.ctx.overhead_model.dsq_contention * *earlier;
+            self.ctx.charge_overhead(cpu, cost);
+            *earlier += 1;
+        }
+    }
+
+    // Runs a per-CPU callback, unless the scheduler has been ejected, and
+    // charges its cost to `cpu`
+    fn call<R>(
+        &mut self,
+        cpu: CpuId,
+        callback: Callback,
+        f: impl FnOnce(&mut S, &mut KernelCtx) -> R,
+    ) -> Option<R> {
+        let (ops, ctx) = self.ops()?;
+        ctx.take_dsq_scanned();
+        let ret = f(ops, ctx);
+        let scanned = self.ctx.take_dsq_scanned();
+        let cost = self.ctx.overhead_model.callback_cost(callback, scanned);
+        self.ctx.charge_overhead(cpu, cost);
+        Some(ret)
+    }
+
+    // The scheduler's callbacks, unless it has been ejected
+    fn ops(&mut self) -> Option<(&mut S, &mut KernelCtx)> {
+        if self.exit_reason.is_some() {
+            return None;
+        }
+        Some((&mut self.scheduler, &mut self.ctx))
+    }
+
+    // Like the kernel reverting to the fair class after a scheduler error: the
+    // scheduler is exited, and every queued task moves to the global DSQ, which
+    // the core then serves in FIFO order on its own.
+    fn eject(&mut self, reason: String) {
+        if self.exit_reason.is_some() {
+            return;
+        }
+        self.scheduler.exit(&mut self.ctx);
+        self.events.push(SchedCoreEvent::SchedulerEjected {
+            reason: reason.clone(),
+        });
+        self.exit_reason = Some(reason);
+
+        let global = self.ctx.global_dsq();
+        let dsqs: Vec<DsqId> = self.ctx.dsqs.keys().filter(|&dsq| dsq != global).collect();
+        for dsq in dsqs {
+            self.move_all_to_global(dsq);
+        }
+    }
+
+    fn move_all_to_global(&mut self, dsq: DsqId) {
+        let global = self.ctx.global_dsq();
+        while let Some(task) = self.ctx.dsq_pop(dsq) {
+            let slice = self
+                .ctx
+                .task(task)
+                .allocated_timeslice
+                .expect("Task on DSQ must have slice");
+            self.ctx.dsq_push_fifo(global, task, slice);
+        }
+    }
+
+    // Per-CPU activity during the most recent tick
+    pub fn last_activity(&self) -> &[CpuActivity] {
+        &self.activity
+    }
+
+    pub fn exit_reason(&self) -> Option<&str> {
+        self.exit_reason.as_deref()
+    }
+
+    // Offlining an offline CPU, or onlining an online one, does nothing
+    pub fn cpu_offline(&mut self, cpu: CpuId) {
+        if !self.ctx.cpus[cpu].online {
+            return;
+        }
+        assert!(
+            self.ctx.cpus.iter().filter(|cpu| cpu.online).count() > 1,
+            "Cannot offline the last online CPU"
+        );
+        self.ctx.cpus[cpu].online = false;
+        self.ctx.cpus[cpu].waking_until = None;
+        self.events.push(SchedCoreEvent::CpuOffline { cpu });
+        if self.ctx.cpus[cpu].stolen.take().is_some() {
+            self.events.push(SchedCoreEvent::HigherClassEnd { cpu });
+        }
+
+        let current = self.ctx.cpus[cpu].current;
+        if let Some(task) = current {
+            self.put_prev(cpu, task, TaskState::Runnable, None);
+        }
+        if let Some((ops, ctx)) = self.ops() {
+            ops.cpu_offline(ctx, cpu);
+        }
+
+        // Everything on the CPU migrates away through enqueue()
+        let dsq = self.ctx.per_cpu_dsq(cpu);
+        let stranded: Vec<TaskId> = current
+            .into_iter()
+            .chain(std::iter::from_fn(|| self.ctx.dsq_pop(dsq)))
+            .collect();
+        for task in stranded {
+            self.requeue(task, cpu);
+        }
+        self.handle_reenqueue_requests();
+    }
+
+    pub fn cpu_online(&mut self, cpu: CpuId) {
+        if self.ctx.cpus[cpu].online {
+            return;
+        }
+        self.ctx.cpus[cpu].online = true;
+        self.events.push(SchedCoreEvent::CpuOnline { cpu });
+        if let Some((ops, ctx)) = self.ops() {
+            ops.cpu_online(ctx, cpu);
+        }
+        self.handle_reenqueue_requests();
+    }
+
+    // Tasks on an offline CPU's local DSQ would never run. Hotplug-aware
+    // schedulers get them moved to the global DSQ; anything else is ejected.
+    fn check_offline_dispatches(&mut self) {
+        for cpu in 0..self.ctx.cpus.len() {
+            let dsq = self.ctx.per_cpu_dsq(cpu);
+            if self.ctx.cpus[cpu].online || self.ctx.dsq_nr_queued(dsq) == 0 {
+                continue;
+            }
+            if S::HOTPLUG_AWARE {
+                self.move_all_to_global(dsq);
+            } else {
+                self.eject(format!("dispatched to offline CPU {cpu}"));
+            }
+        }
+    }
+
+    // Hand `cpu` to higher-class activity for `duration` ticks, starting this tick.
+    // Overlapping activity extends the steal; a CPU-releasing class takes
+    // precedence over interrupts.
+    pub fn steal_cpu(&mut self, cpu: CpuId, class: HigherClass, duration: Ticks) {
+        debug_assert!(duration > 0, "Steal duration must be nonzero");
+        let prev = self.ctx.cpus[cpu].stolen;
+        let until = self.ctx.now + duration;
+        let steal = match prev {
+            Some(prev) => CpuSteal {
+                class: if prev.class.releases_cpu() {
+                    prev.class
+                } else {
+                    class
+                },
+                until: cmp::max(prev.until, until),
+            },
+            None => CpuSteal { class, until },
+        };
+        self.ctx.cpus[cpu].stolen = Some(steal);
+        if let Some((state, latency)) = self.ctx.exit_idle(cpu) {
+            self.events.push(SchedCoreEvent::CpuIdleExit {
+                cpu,
+                state,
+                latency,
             });
-            self.scheduler
-                .enqueue(&mut self.ctx, current_task_id, SCX_ENQ_REENQ, cpu);
+        }
+        if prev.is_none_or(|prev| prev.class != steal.class) {
+            self.events.push(SchedCoreEvent::HigherClassStart {
+                cpu,
+                class: steal.class,
+            });
+        }
+
+        let already_released = prev.is_some_and(|prev| prev.class.releases_cpu());
+        if !steal.class.releases_cpu() || already_released {
+            return;
+        }
+
+        // The current task keeps the rest of its slice at the head of the local DSQ
+        if let Some(task) = self.ctx.cpus[cpu].current {
+            self.put_prev(cpu, task, TaskState::Runnable, None);
+            let task_state = self.ctx.task(task);
+            let remaining = task_state
+                .allocated_timeslice
+                .expect("Running task has an unset timeslice")
+                .saturating_sub(task_state.consumed_timeslice);
+            let dsq = self.ctx.per_cpu_dsq(cpu);
+            self.ctx.dsq_push_fifo_head(dsq, task, remaining);
+            self.events
+                .push(SchedCoreEvent::TaskPreempted { task, cpu });
+        }
+
+        if let Some((ops, ctx)) = self.ops() {
+            ops.cpu_release(ctx, cpu, steal.class);
+        }
+        self.handle_reenqueue_requests();
+    }
+
+    fn end_expired_steals(&mut self) {
+        for cpu in 0..self.ctx.cpus.len() {
+            let Some(steal) = self.ctx.cpus[cpu].stolen else {
+                continue;
+            };
+            if steal.until > self.ctx.now {
+                continue;
+            }
+            self.ctx.cpus[cpu].stolen = None;
+            self.events.push(SchedCoreEvent::HigherClassEnd { cpu });
+            if steal.class.releases_cpu()
+                && let Some((ops, ctx)) = self.ops()
+            {
+                ops.cpu_acquire(ctx, cpu);
+                self.handle_reenqueue_requests();
+            }
+        }
+    }
+
+    fn handle_reenqueue_requests(&mut self) {
+        for cpu in self.ctx.drain_reenqueue_requests() {
+            let dsq = self.ctx.per_cpu_dsq(cpu);
+            let tasks: Vec<TaskId> = std::iter::from_fn(|| self.ctx.dsq_pop(dsq)).collect();
+            for task in tasks {
+                self.enqueue(task, SCX_ENQ_REENQ, cpu);
+            }
+        }
+    }
+
+    // Tasks held by throttling go back to the scheduler once no group above them is throttled
+    fn refresh_cgroup_bandwidth(&mut self) {
+        let unthrottled = self.ctx.refresh_cgroup_bandwidth();
+        if unthrottled.is_empty() {
+            return;
+        }
+        self.events.extend(
+            unthrottled
+                .into_iter()
+                .map(|cgroup| SchedCoreEvent::CgroupUnthrottled { cgroup }),
+        );
+
+        let (released, held) = std::mem::take(&mut self.throttled_tasks)
+            .into_iter()
+            .partition(|&(task, _)| !self.ctx.task_is_throttled(task));
+        self.throttled_tasks = held;
+        for (task, cpu) in released {
+            self.enqueue(task, SCX_ENQ_REENQ, cpu);
+        }
+    }
+
+    // Kicks issued since the last tick take effect before any CPU is scheduled.
+    // Idle CPUs are scheduled every tick anyway, so only preemption needs handling:
+    // the current task is descheduled as Runnable, as if its slice had expired.
+    fn handle_kicks(&mut self) {
+        for (cpu, flags) in self.ctx.drain_kicks() {
+            if flags & SCX_KICK_PREEMPT == 0 {
+                continue;
+            }
+            if let Some(task) = self.ctx.cpus[cpu].current {
+                self.deschedule(cpu, task, TaskState::Runnable, None);
+                self.events
+                    .push(SchedCoreEvent::TaskPreempted { task, cpu });
+            }
         }
     }
 
@@ -110,28 +688,69 @@ impl<S: Scheduler> SchedCore<S> {
     // 2. Pull from global DSQ
     // 3. Call ops->dispatch() to fill local, per-CPU DSQ
     fn schedule_cpu(&mut self, cpu: CpuId) {
-        if !self.ctx.cpu_is_idle(cpu) {
+        if !self.ctx.cpu_is_idle(cpu) || self.ctx.cpu_is_waking(cpu) {
             return;
         }
 
-        let maybe_next_task = self
-            .ctx
-            .dsq_pop(self.ctx.per_cpu_dsq(cpu))
-            .or_else(|| self.ctx.dsq_pop(self.ctx.global_dsq()))
-            .or_else(|| {
-                self.scheduler.dispatch(&mut self.ctx, cpu);
-                self.ctx.dsq_pop(self.ctx.per_cpu_dsq(cpu))
-            });
+        // Tasks of throttled groups are held back as they are pulled, until a runnable one is found
+        let maybe_next_task = loop {
+            let next = self
+                .ctx
+                .dsq_pop(self.ctx.per_cpu_dsq(cpu))
+                .or_else(|| self.ctx.dsq_pop(self.ctx.global_dsq()))
+                .or_else(|| {
+                    self.call(cpu, Callback::Dispatch, |ops, ctx| ops.dispatch(ctx, cpu))?;
+                    self.ctx.dsq_pop(self.ctx.per_cpu_dsq(cpu))
+                });
+            match next {
+                Some(task) if self.ctx.task_is_throttled(task) => {
+                    self.throttled_tasks.push((task, cpu));
+                }
+                next => break next,
+            }
+        };
 
         if let Some(task) = maybe_next_task {
+            // A CPU in an idle state only runs the task once it has left it
+            if let Some((state, latency)) = self.ctx.exit_idle(cpu) {
+                self.events.push(SchedCoreEvent::CpuIdleExit {
+                    cpu,
+                    state,
+                    latency,
+                });
+                if latency > 0 {
+                    let slice = self
+                        .ctx
+                        .task(task)
+                        .allocated_timeslice
+                        .expect("Task on DSQ must have slice");
+                    self.ctx
+                        .dsq_push_fifo_head(self.ctx.per_cpu_dsq(cpu), task, slice);
+                    self.ctx.cpus[cpu].waking_until = Some(self.ctx.now + latency);
+                    return;
+                }
+            }
+            self.ctx.cpus[cpu].waking_until = None;
+            if let Some(from) = self.ctx.place_task(cpu, task) {
+                self.events.push(SchedCoreEvent::TaskMigrate {
+                    task,
+                    from,
+                    to: cpu,
+                });
+            }
+            if self.ctx.cpus[cpu].last_task.replace(task) != Some(task) {
+                let cost = self.ctx.overhead_model.context_switch;
+                self.ctx.charge_overhead(cpu, cost);
+            }
             let prev_state = self.ctx.set_running(cpu, task);
-            self.scheduler.running(&mut self.ctx, task);
+            self.call(cpu, Callback::Running, |ops, ctx| ops.running(ctx, task));
 
             self.events.extend([
                 SchedCoreEvent::TaskStateChange {
                     task,
                     from: prev_state,
                     to: TaskState::Running,
+                    reason: None,
                 },
                 SchedCoreEvent::CpuCurrentChange {
                     cpu,
@@ -142,28 +761,185 @@ impl<S: Scheduler> SchedCore<S> {
         }
     }
 
-    pub fn create_task(&mut self, required_service: Ticks, weight: u64) -> TaskId {
-        let task = self.ctx.create_task(required_service, weight);
-        self.scheduler.enable(&mut self.ctx, task);
+    pub fn create_task(
+        &mut self,
+        required_service: Ticks,
+        weight: u64,
+        deadline: Option<Ticks>,
+        cgroup: CgroupId,
+        attrs: TaskAttrs,
+    ) -> TaskId {
+        let task = self
+            .ctx
+            .create_task(required_service, weight, deadline, cgroup, attrs);
+        if let Some((ops, ctx)) = self.ops() {
+            ops.enable(ctx, task);
+        }
         task
     }
 
+    // Frees a completed task; its TaskId is not reused
+    pub fn reap_task(&mut self, task: TaskId) {
+        if let Some((ops, ctx)) = self.ops() {
+            ops.disable(ctx, task);
+        }
+        self.ctx.reap_task(task);
+    }
+
+    pub fn set_task_weight(&mut self, task: TaskId, weight: u64) {
+        debug_assert!(weight > 0, "Task weight must be nonzero");
+        self.ctx.task_mut(task).weight = weight;
+        if let Some((ops, ctx)) = self.ops() {
+            ops.set_weight(ctx, task, weight);
+        }
+    }
+
+    pub fn create_cgroup(&mut self, parent: CgroupId, name: &str) -> CgroupId {
+        let cgroup = self.ctx.create_cgroup(parent, name);
+        if let Some((ops, ctx)) = self.ops() {
+            ops.cgroup_init(ctx, cgroup);
+        }
+        cgroup
+    }
+
+    // Look up a cgroup by path (e.g. "/a/b"), creating any missing components
+    pub fn ensure_cgroup(&mut self, path: &str) -> CgroupId {
+        let mut cgroup = self.ctx.root_cgroup();
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            let child_path = cgroup::child_path(&self.ctx.cgroup(cgroup).path, name);
+            cgroup = match self.ctx.cgroup_lookup(&child_path) {
+                Some(child) => child,
+                None => self.create_cgroup(cgroup, name),
+            };
+        }
+        cgroup
+    }
+
+    pub fn remove_cgroup(&mut self, cgroup: CgroupId) {
+        if let Some((ops, ctx)) = self.ops() {
+            ops.cgroup_exit(ctx, cgroup);
+        }
+        self.ctx.remove_cgroup(cgroup);
+    }
+
+    pub fn set_cgroup_weight(&mut self, cgroup: CgroupId, weight: u64) {
+        debug_assert!((1..=10000).contains(&weight), "cpu.weight out of range");
+        self.ctx.cgroup_mut(cgroup).weight = weight;
+        if let Some((ops, ctx)) = self.ops() {
+            ops.cgroup_set_weight(ctx, cgroup, weight);
+        }
+    }
+
+    pub fn set_idle_states(&mut self, cpu: CpuId, states: Vec<IdleState>) {
+        assert!(
+            states.is_sorted_by_key(|state| (state.exit_latency, state.target_residency)),
+            "Idle states of CPU {cpu} must be ordered shallowest first"
+        );
+        let cpu = &mut self.ctx.cpus[cpu];
+        cpu.idle_states = states;
+        cpu.idle_state = None;
+    }
+
+    pub fn set_cache_model(&mut self, model: Option<CacheModel>) {
+        self.ctx.cache_model = model;
+    }
+
+    pub fn set_overhead_model(&mut self, model: OverheadModel) {
+        self.ctx.overhead_model = model;
+    }
+
+    pub fn set_cpu_order(&mut self, order: CpuOrder) {
+        self.cpu_order = CpuOrderGen::new(order);
+    }
+
+    pub fn create_lock(&mut self, kind: LockKind, priority_inheritance: bool) -> LockId {
+        let id = self.ctx.locks.len();
+        self.ctx
+            .locks
+            .push(Lock::new(id, kind, priority_inheritance));
+        id
+    }
+
+    pub fn set_governor(&mut self, governor: Governor) {
+        self.ctx.governor = governor;
+    }
+
+    // Applies from the next tick, counting usage already charged this period.
+    // Lifting the limit releases the group's held tasks on the next tick.
+    pub fn set_cgroup_max(&mut self, cgroup: CgroupId, max: Option<CpuMax>) {
+        debug_assert!(
+            max.is_none_or(|max| max.quota > 0 && max.period > 0),
+            "cpu.max quota and period must be nonzero"
+        );
+        self.ctx.cgroup_mut(cgroup).max = max;
+    }
+
+    pub fn move_task(&mut self, task: TaskId, to: CgroupId) {
+        let from = self.ctx.task(task).cgroup;
+        if from == to {
+            return;
+        }
+        self.ctx.task_mut(task).cgroup = to;
+        if let Some((ops, ctx)) = self.ops() {
+            ops.cgroup_move(ctx, task, from, to);
+        }
+        // A task held by its old group's throttling is free to run in a new one
+        if !self.ctx.task_is_throttled(task)
+            && let Some(i) = self.throttled_tasks.iter().position(|&(t, _)| t == task)
+        {
+            let (_, cpu) = self.throttled_tasks.remove(i);
+            self.enqueue(task, SCX_ENQ_REENQ, cpu);
+        }
+    }
+
     pub fn wake_task(&mut self, task: TaskId, wakeup_cpu: CpuId) {
+        self.wake_task_flags(task, wakeup_cpu, SCX_WAKE_TTWU, None);
+    }
+
+    // Wakeup of `task` by another task, from the CPU the waker last ran on.
+    // SCX_WAKE_FORK for new children, SCX_WAKE_TTWU otherwise.
+    pub fn wake_task_by(&mut self, task: TaskId, waker: TaskId, wake_flags: WakeFlags) {
+        let wakeup_cpu = self.ctx.task(waker).last_cpu.expect("Waker has never run");
+        self.wake_task_flags(task, wakeup_cpu, wake_flags, Some(waker));
+    }
+
+    fn wake_task_flags(
+        &mut self,
+        task: TaskId,
+        wakeup_cpu: CpuId,
+        wake_flags: WakeFlags,
+        waker: Option<TaskId>,
+    ) {
         self.ctx.mark_runnable(task);
+        if waker.is_some() {
+            self.ctx.task_mut(task).last_waker = waker;
+        }
+        self.events.push(SchedCoreEvent::TaskWakeup {
+            task,
+            waker,
+            cpu: wakeup_cpu,
+        });
+        let decision = self.call(wakeup_cpu, Callback::SelectCpu, |ops, ctx| {
+            ops.select_cpu(ctx, task, wakeup_cpu, wake_flags)
+        });
+        // As in enqueue_task_scx(), once the CPU is chosen and before the
+        // task is queued, whether or not it was dispatched directly
+        self.call(wakeup_cpu, Callback::Runnable, |ops, ctx| {
+            ops.runnable(ctx, task, SCX_ENQ_WAKEUP)
+        });
 
-        match self.scheduler.select_cpu(&mut self.ctx, task, wakeup_cpu) {
+        match decision.unwrap_or(SelectCpuDecision::EnqueueOnDefault) {
             SelectCpuDecision::DirectDispatch(cpu, slice) => {
                 let dsq = self.ctx.per_cpu_dsq(cpu);
                 self.ctx.dsq_push_fifo(dsq, task, slice);
             }
             SelectCpuDecision::EnqueueOn(cpu) => {
                 let flags: EnqueueFlags = SCX_ENQ_WAKEUP | SCX_ENQ_CPU_SELECTED;
-                self.scheduler.enqueue(&mut self.ctx, task, flags, cpu);
+                self.enqueue(task, flags, cpu);
             }
             SelectCpuDecision::EnqueueOnDefault => {
                 let flags: EnqueueFlags = SCX_ENQ_WAKEUP;
-                self.scheduler
-                    .enqueue(&mut self.ctx, task, flags, wakeup_cpu);
+                self.enqueue(task, flags, wakeup_cpu);
             }
         }
     }
@@ -1,4 +1,4 @@
-use crate::core::{CpuId, TaskId, TaskState};
+use crate::core::{BlockReason, CgroupId, CpuId, HigherClass, LockId, TaskId, TaskState, Ticks};
 
 #[derive(Debug)]
 pub enum SchedCoreEvent {
@@ -6,14 +6,93 @@ pub enum SchedCoreEvent {
         task: TaskId,
         from: TaskState,
         to: TaskState,
+        // Set when `to` is Blocked
+        reason: Option<BlockReason>,
     },
     CpuCurrentChange {
         cpu: CpuId,
         from: Option<TaskId>,
         to: Option<TaskId>,
     },
+    TaskWakeup {
+        task: TaskId,
+        // None for wakeups from outside the system
+        waker: Option<TaskId>,
+        cpu: CpuId,
+    },
+    TaskMigrate {
+        task: TaskId,
+        from: CpuId,
+        to: CpuId,
+    },
+    // Taken off `cpu` while runnable by a preempting kick or a higher class,
+    // rather than by slice expiry or throttling
+    TaskPreempted {
+        task: TaskId,
+        cpu: CpuId,
+    },
     // CPU idle even after dispatch()
     CpuIdle {
         cpu: CpuId,
     },
+    // cpu.max quota exhausted; the group's tasks are held until its next period
+    CgroupThrottled {
+        cgroup: CgroupId,
+    },
+    CgroupUnthrottled {
+        cgroup: CgroupId,
+    },
+    HigherClassStart {
+        cpu: CpuId,
+        class: HigherClass,
+    },
+    HigherClassEnd {
+        cpu: CpuId,
+    },
+    CpuPerfChange {
+        cpu: CpuId,
+        from: u64,
+        to: u64,
+    },
+    CpuIdleEnter {
+        cpu: CpuId,
+        state: usize,
+    },
+    CpuIdleExit {
+        cpu: CpuId,
+        state: usize,
+        latency: Ticks,
+    },
+    CpuOnline {
+        cpu: CpuId,
+    },
+    CpuOffline {
+        cpu: CpuId,
+    },
+    // `task` found `lock` held by `holder` and started waiting
+    LockContended {
+        task: TaskId,
+        lock: LockId,
+        holder: TaskId,
+    },
+    // Handed over on release, after `waited` ticks
+    LockAcquired {
+        task: TaskId,
+        lock: LockId,
+        waited: Ticks,
+    },
+    LockReleased {
+        task: TaskId,
+        lock: LockId,
+    },
+    // A lock holder was descheduled while runnable, with a heavier task waiting
+    LockHolderPreempted {
+        task: TaskId,
+        lock: LockId,
+        waiter: TaskId,
+    },
+    // The scheduler misbehaved and the core took over; see SchedCore::exit_reason
+    SchedulerEjected {
+        reason: String,
+    },
 }
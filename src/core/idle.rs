@@ -0,0 +1,22 @@
+use super::state::Ticks;
+
+// A CPU idle state, like a cpuidle C-state. Deeper states save more power but
+// take longer to leave.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdleState {
+    pub name: String,
+    // Delay between a task being picked and the CPU running it
+    pub exit_latency: Ticks,
+    // Minimum idle time for the state to be worth entering
+    pub target_residency: Ticks,
+}
+
+impl IdleState {
+    pub fn new(name: &str, exit_latency: Ticks, target_residency: Ticks) -> Self {
+        Self {
+            name: name.to_string(),
+            exit_latency,
+            target_residency,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+
+use super::state::{TaskId, Ticks};
+
+// Index into KernelCtx::locks
+pub type LockId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    // Waiters keep their CPU, making no progress until the lock is handed to them
+    Spin,
+    // Waiters sleep until the holder wakes them on release
+    Sleep,
+}
+
+#[derive(Debug)]
+pub struct Lock {
+    pub id: LockId,
+    pub kind: LockKind,
+    // Boost the holder to the weight of its heaviest waiter
+    pub priority_inheritance: bool,
+    pub holder: Option<TaskId>,
+    // The holder's own weight, while boosted
+    pub holder_weight: Option<u64>,
+    // Handed over in FIFO order on release
+    pub waiters: VecDeque<TaskId>,
+}
+
+// Part of a task's burst that runs with `lock` held: `len` ticks of service,
+// starting once the task has received `start`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CriticalSection {
+    pub lock: LockId,
+    pub start: Ticks,
+    pub len: Ticks,
+}
+
+impl Lock {
+    pub(crate) fn new(id: LockId, kind: LockKind, priority_inheritance: bool) -> Self {
+        Self {
+            id,
+            kind,
+            priority_inheritance,
+            holder: None,
+            holder_weight: None,
+            waiters: VecDeque::new(),
+        }
+    }
+}
@@ -1,8 +1,31 @@
+pub mod cache;
+pub mod cgroup;
+pub mod class;
 pub mod driver;
 pub mod event;
+pub mod idle;
+pub mod lock;
 pub mod observer;
+pub mod order;
+pub mod overhead;
+pub mod perf;
+pub mod prio;
 pub mod state;
+pub mod topology;
 
+pub use cache::CacheModel;
+pub use cgroup::{CGROUP_WEIGHT_DFL, Cgroup, CgroupId, CpuMax};
+pub use class::{CpuSteal, HigherClass};
 pub use driver::SchedCore;
 pub use event::SchedCoreEvent;
-pub use state::{CpuId, CpuState, Dsq, DsqId, KernelCtx, Task, TaskId, TaskState, Ticks, Vtime};
+pub use idle::IdleState;
+pub use lock::{CriticalSection, Lock, LockId, LockKind};
+pub use order::CpuOrder;
+pub use overhead::{Callback, OverheadModel};
+pub use perf::{Governor, SCHED_CAPACITY_SCALE, SCX_CPUPERF_ONE};
+pub use prio::{NICE_0_WEIGHT, nice_to_weight, weight_to_nice};
+pub use state::{
+    BlockReason, CpuActivity, CpuId, CpuState, Dsq, DsqId, KernelCtx, Task, TaskAttrs, TaskId,
+    TaskState, Ticks, Vtime,
+};
+pub use topology::{CpuTopo, Topology};
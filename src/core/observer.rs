@@ -1,19 +1,24 @@
 use super::state::{KernelCtx, TaskState};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Observer {
     step: u64,
 }
 
 impl Observer {
     pub fn new() -> Self {
-        Self { step: 0 }
+        Self::default()
     }
 
     pub fn observe(&mut self, core: &KernelCtx) {
         self.step += 1;
 
         for cpu in &core.cpus {
+            debug_assert!(
+                cpu.online || cpu.current.is_none(),
+                "Offline CPU {} has a current task",
+                cpu.id
+            );
             if let Some(task_id) = cpu.current {
                 let task = core.task(task_id);
                 debug_assert_eq!(
@@ -0,0 +1,45 @@
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use super::state::{CpuId, Ticks};
+
+// Order in which CPUs are scheduled and ticked within a tick. Sequential order
+// lets low-numbered CPUs win every race for a shared DSQ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CpuOrder {
+    #[default]
+    Sequential,
+    // Starts one CPU later every tick
+    Rotating,
+    Random {
+        seed: u64,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct CpuOrderGen {
+    order: CpuOrder,
+    rng: StdRng,
+}
+
+impl CpuOrderGen {
+    pub fn new(order: CpuOrder) -> Self {
+        let seed = match order {
+            CpuOrder::Random { seed } => seed,
+            _ => 0,
+        };
+        Self {
+            order,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn next(&mut self, num_cpus: usize, now: Ticks) -> Vec<CpuId> {
+        let mut cpus: Vec<CpuId> = (0..num_cpus).collect();
+        match self.order {
+            CpuOrder::Sequential => {}
+            CpuOrder::Rotating => cpus.rotate_left(now as usize % num_cpus),
+            CpuOrder::Random { .. } => cpus.shuffle(&mut self.rng),
+        }
+        cpus
+    }
+}
@@ -0,0 +1,50 @@
+// Time the CPU spends outside of tasks. Costs are in SCHED_CAPACITY_SCALE
+// units of a tick, and are paid out of the CPU's next ticks before its task
+// makes any progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OverheadModel {
+    // Switching a CPU to a different task than the one it last ran
+    pub context_switch: u64,
+    pub select_cpu: u64,
+    pub enqueue: u64,
+    pub dispatch: u64,
+    pub runnable: u64,
+    pub running: u64,
+    pub stopping: u64,
+    pub quiescent: u64,
+    pub tick: u64,
+    // Added to a callback's cost for each task queued in a DSQ it pushes to or
+    // pops from
+    pub dsq_per_task: u64,
+    // Lock contention on shared DSQs: the k-th CPU in a tick to pop from the
+    // same DSQ waits k - 1 times this long
+    pub dsq_contention: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Callback {
+    SelectCpu,
+    Enqueue,
+    Dispatch,
+    Runnable,
+    Running,
+    Stopping,
+    Quiescent,
+    Tick,
+}
+
+impl OverheadModel {
+    pub fn callback_cost(&self, callback: Callback, dsq_len: u64) -> u64 {
+        let fixed = match callback {
+            Callback::SelectCpu => self.select_cpu,
+            Callback::Enqueue => self.enqueue,
+            Callback::Dispatch => self.dispatch,
+            Callback::Runnable => self.runnable,
+            Callback::Running => self.running,
+            Callback::Stopping => self.stopping,
+            Callback::Quiescent => self.quiescent,
+            Callback::Tick => self.tick,
+        };
+        fixed + self.dsq_per_task * dsq_len
+    }
+}
@@ -0,0 +1,44 @@
+// CPU capacity and frequency scaling. Capacities are relative to the fastest
+// CPU in the system, and performance levels to a CPU's own maximum frequency,
+// both as in the kernel.
+
+pub const SCHED_CAPACITY_SCALE: u64 = 1024;
+pub const SCX_CPUPERF_ONE: u64 = 1024;
+
+// Lowest performance level a CPU clocks down to, unless configured otherwise
+pub const CPUPERF_MIN_DFL: u64 = SCX_CPUPERF_ONE / 4;
+
+// Each tick, utilization moves 1/2^UTIL_AVG_SHIFT of the way toward the last
+// tick's busy/idle sample
+const UTIL_AVG_SHIFT: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Governor {
+    // Always run at the maximum frequency
+    #[default]
+    Performance,
+    // Always run at the minimum frequency
+    Powersave,
+    // Frequency follows recent utilization with 25% headroom, like schedutil.
+    // A target set by the scheduler through cpuperf_set() takes the place of
+    // utilization.
+    Schedutil,
+}
+
+impl Governor {
+    pub fn next_perf(&self, util_avg: u64, target: Option<u64>, perf_min: u64) -> u64 {
+        match self {
+            Self::Performance => SCX_CPUPERF_ONE,
+            Self::Powersave => perf_min,
+            Self::Schedutil => {
+                let demand = target.unwrap_or(util_avg);
+                (demand + demand / 4).clamp(perf_min, SCX_CPUPERF_ONE)
+            }
+        }
+    }
+}
+
+pub(crate) fn update_util_avg(util_avg: u64, busy: bool) -> u64 {
+    let sample = if busy { SCX_CPUPERF_ONE } else { 0 };
+    util_avg - (util_avg >> UTIL_AVG_SHIFT) + (sample >> UTIL_AVG_SHIFT)
+}
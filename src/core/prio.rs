@@ -0,0 +1,35 @@
+// Nice levels and load weights, as in the kernel's kernel/sched/core.c
+
+pub const MIN_NICE: i32 = -20;
+pub const MAX_NICE: i32 = 19;
+
+pub const NICE_0_WEIGHT: u64 = 1024;
+
+// Each nice level is ~10% more or less CPU than its neighbor
+#[rustfmt::skip]
+pub const SCHED_PRIO_TO_WEIGHT: [u64; 40] = [
+    /* -20 */ 88761, 71755, 56483, 46273, 36291,
+    /* -15 */ 29154, 23254, 18705, 14949, 11916,
+    /* -10 */ 9548, 7620, 6100, 4904, 3906,
+    /*  -5 */ 3121, 2501, 1991, 1586, 1277,
+    /*   0 */ 1024, 820, 655, 526, 423,
+    /*   5 */ 335, 272, 215, 172, 137,
+    /*  10 */ 110, 87, 70, 56, 45,
+    /*  15 */ 36, 29, 23, 18, 15,
+];
+
+pub fn nice_to_weight(nice: i32) -> u64 {
+    assert!(
+        (MIN_NICE..=MAX_NICE).contains(&nice),
+        "Nice value {nice} out of range"
+    );
+    SCHED_PRIO_TO_WEIGHT[(nice - MIN_NICE) as usize]
+}
+
+// Nice level whose weight is closest to `weight`
+pub fn weight_to_nice(weight: u64) -> i32 {
+    let index = (0..SCHED_PRIO_TO_WEIGHT.len())
+        .min_by_key(|&i| SCHED_PRIO_TO_WEIGHT[i].abs_diff(weight))
+        .expect("weight table is not empty");
+    MIN_NICE + index as i32
+}
@@ -3,7 +3,19 @@ use rustc_hash::FxHashMap;
 use slotmap::{SlotMap, new_key_type};
 use std::collections::VecDeque;
 
-// Index into Task Vec
+use super::{
+    cache::CacheModel,
+    cgroup::{self, Cgroup, CgroupId},
+    class::CpuSteal,
+    idle::IdleState,
+    lock::{CriticalSection, Lock, LockId},
+    overhead::OverheadModel,
+    perf::{self, CPUPERF_MIN_DFL, Governor, SCHED_CAPACITY_SCALE, SCX_CPUPERF_ONE},
+    topology::Topology,
+};
+use crate::scheduler::KickFlags;
+
+// Key into KernelCtx::tasks; never reused
 pub type TaskId = usize;
 pub type CpuId = usize;
 pub type Ticks = u64;
@@ -22,6 +34,22 @@ pub enum TaskState {
     Completed,
 }
 
+// Why a task went to sleep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockReason {
+    // Reached its block_at service point, such as a Sim fork point or barrier
+    BlockAt,
+    // Waiting for a sleeping lock to be handed to it
+    Lock(LockId),
+}
+
+// Descriptive attributes that schedulers may use to classify tasks
+#[derive(Debug, Clone, Default)]
+pub struct TaskAttrs {
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
 #[derive(Debug)]
 pub struct Task {
     pub id: TaskId,
@@ -29,23 +57,84 @@ pub struct Task {
     pub current_cpu: Option<CpuId>,
     pub required_service: Ticks,
     pub consumed_service: Ticks,
+    // Partial service, in SCHED_CAPACITY_SCALE units of a full-speed tick
+    pub service_remainder: u64,
     pub allocated_timeslice: Option<Ticks>,
     pub consumed_timeslice: Ticks,
     pub completion_time: Option<Ticks>,
     pub vtime: u64,
     pub weight: u64,
+    // Absolute deadline, if the task has one
+    pub deadline: Option<Ticks>,
+    pub cgroup: CgroupId,
+    pub attrs: TaskAttrs,
+    // Where and until when the task last ran
+    pub last_cpu: Option<CpuId>,
+    pub last_ran: Ticks,
+    pub nr_migrations: u64,
+    pub nr_llc_migrations: u64,
+    pub nr_node_migrations: u64,
+    // Cache refill work still owed, and in total, in SCHED_CAPACITY_SCALE units
+    pub cache_debt: u64,
+    pub cache_penalty: u64,
+    pub last_waker: Option<TaskId>,
+    // Service at which the task goes to sleep until something wakes it
+    pub block_at: Option<Ticks>,
+    // Remaining critical sections; the first is held or next to be entered
+    pub critical_sections: VecDeque<CriticalSection>,
+    pub lock_held: Option<LockId>,
+    // Lock being waited on, and since when
+    pub lock_waiting: Option<(LockId, Ticks)>,
+    // Total time spent waiting for locks
+    pub lock_wait: Ticks,
 }
 
 #[derive(Debug)]
 pub struct CpuState {
     pub id: CpuId,
     pub current: Option<TaskId>,
+    pub online: bool,
+    // Taken over by a higher sched class or interrupt activity
+    pub stolen: Option<CpuSteal>,
+    // Relative to the fastest CPU, in SCHED_CAPACITY_SCALE units
+    pub capacity: u64,
+    // Current and lowest frequency, relative to this CPU's maximum
+    pub perf_cur: u64,
+    pub perf_min: u64,
+    // Requested through cpuperf_set()
+    pub perf_target: Option<u64>,
+    // Decaying average of busy ticks, in SCX_CPUPERF_ONE units
+    pub util_avg: u64,
+    // Available idle states, shallowest first, and the deepest one allowed
+    pub idle_states: Vec<IdleState>,
+    pub idle_state_limit: Option<usize>,
+    pub idle_since: Option<Ticks>,
+    pub idle_state: Option<usize>,
+    // Leaving an idle state, with a task waiting at the head of the local DSQ
+    pub waking_until: Option<Ticks>,
+    // Task switched to most recently, for context switch accounting
+    pub last_task: Option<TaskId>,
+    // Overhead still to be paid, and in total, in SCHED_CAPACITY_SCALE units
+    pub overhead_debt: u64,
+    pub overhead: u64,
+}
+
+// What a CPU did during the last tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuActivity {
+    pub online: bool,
+    // The task that made progress, if any
+    pub ran: Option<TaskId>,
+    // Running a task or a higher class
+    pub busy: bool,
+    pub perf: u64,
+    pub idle_state: Option<usize>,
 }
 
 // KeyedPriorityQueue is a max-heap, so we need to flip-flop Vtime's Ord
 impl PartialOrd for Vtime {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        other.0.partial_cmp(&self.0)
+        Some(self.cmp(other))
     }
 }
 
@@ -90,18 +179,52 @@ impl Dsq {
 pub struct KernelCtx {
     pub now: Ticks,
     pub cpus: Vec<CpuState>,
-    pub tasks: Vec<Task>,
+    // Live tasks, and completed ones until reaped
+    pub tasks: FxHashMap<TaskId, Task>,
     pub dsqs: SlotMap<DsqId, Dsq>,
     pub task_to_dsq: FxHashMap<TaskId, DsqId>,
     pub global_dsq_id: DsqId,
     pub per_cpu_dsq_ids: Vec<DsqId>,
+    pub cgroups: SlotMap<CgroupId, Cgroup>,
+    pub root_cgroup_id: CgroupId,
+    pub governor: Governor,
+    pub topology: Topology,
+    pub cache_model: Option<CacheModel>,
+    pub overhead_model: OverheadModel,
+    pub locks: Vec<Lock>,
 
     // Increment upon task creation
     next_task_id: TaskId,
+    // Drained by SchedCore at the start of every tick
+    kicks: Vec<(CpuId, KickFlags)>,
+    // CPUs whose local DSQ should go back through enqueue(), drained by SchedCore
+    // after the callback that requested it
+    reenqueue_requests: Vec<CpuId>,
+    // Sum of DSQ lengths seen by pushes and pops, drained by SchedCore after
+    // each callback to cost it
+    dsq_scanned: u64,
+    // DSQs popped from, drained by SchedCore for contention accounting
+    dsq_pops: Vec<DsqId>,
 }
 
 impl KernelCtx {
     pub fn new(num_cpus: usize) -> Self {
+        Self::with_topology(Topology::flat(num_cpus))
+    }
+
+    pub fn with_capacities(capacities: &[u64]) -> Self {
+        Self::with_topology(Topology::with_capacities(capacities))
+    }
+
+    pub fn with_topology(topology: Topology) -> Self {
+        let num_cpus = topology.num_cpus();
+        for cpu in &topology.cpus {
+            assert!(
+                (1..=SCHED_CAPACITY_SCALE).contains(&cpu.capacity),
+                "CPU capacity {} out of range",
+                cpu.capacity
+            );
+        }
         let mut dsqs = SlotMap::with_capacity_and_key(1 + num_cpus);
 
         // Create global DSQ
@@ -114,21 +237,63 @@ impl KernelCtx {
             per_cpu_dsq_ids.push(dsq_id);
         }
 
+        let mut cgroups = SlotMap::with_key();
+        let root_cgroup_id = cgroups.insert_with_key(|id| Cgroup::new(id, None, "/".to_string()));
+
         Self {
             now: 0,
-            cpus: (0..num_cpus)
-                .map(|id| CpuState { id, current: None })
+            cpus: topology
+                .cpus
+                .iter()
+                .enumerate()
+                .map(|(id, topo)| CpuState {
+                    id,
+                    current: None,
+                    online: true,
+                    stolen: None,
+                    capacity: topo.capacity,
+                    perf_cur: SCX_CPUPERF_ONE,
+                    perf_min: CPUPERF_MIN_DFL,
+                    perf_target: None,
+                    util_avg: 0,
+                    idle_states: Vec::new(),
+                    idle_state_limit: None,
+                    idle_since: None,
+                    idle_state: None,
+                    waking_until: None,
+                    last_task: None,
+                    overhead_debt: 0,
+                    overhead: 0,
+                })
                 .collect(),
-            tasks: Vec::new(),
+            tasks: FxHashMap::default(),
             dsqs,
             task_to_dsq: FxHashMap::default(),
             global_dsq_id,
             per_cpu_dsq_ids,
+            cgroups,
+            root_cgroup_id,
+            governor: Governor::default(),
+            topology,
+            cache_model: None,
+            overhead_model: OverheadModel::default(),
+            locks: Vec::new(),
             next_task_id: 0,
+            kicks: Vec::new(),
+            reenqueue_requests: Vec::new(),
+            dsq_scanned: 0,
+            dsq_pops: Vec::new(),
         }
     }
 
-    pub fn create_task(&mut self, required_service: Ticks, weight: u64) -> TaskId {
+    pub fn create_task(
+        &mut self,
+        required_service: Ticks,
+        weight: u64,
+        deadline: Option<Ticks>,
+        cgroup: CgroupId,
+        attrs: TaskAttrs,
+    ) -> TaskId {
         let id = self.next_task_id;
         self.next_task_id += 1;
 
@@ -138,15 +303,31 @@ impl KernelCtx {
             current_cpu: None,
             required_service,
             consumed_service: 0,
+            service_remainder: 0,
             allocated_timeslice: None,
             consumed_timeslice: 0,
             completion_time: None,
             vtime: 0,
             weight,
+            deadline,
+            cgroup,
+            attrs,
+            last_cpu: None,
+            last_ran: 0,
+            nr_migrations: 0,
+            nr_llc_migrations: 0,
+            nr_node_migrations: 0,
+            cache_debt: 0,
+            cache_penalty: 0,
+            last_waker: None,
+            block_at: None,
+            critical_sections: VecDeque::new(),
+            lock_held: None,
+            lock_waiting: None,
+            lock_wait: 0,
         };
 
-        debug_assert_eq!(self.tasks.len(), id, "TaskId must match Vec index");
-        self.tasks.push(task);
+        self.tasks.insert(id, task);
 
         id
     }
@@ -163,7 +344,14 @@ impl KernelCtx {
         self.dsqs.insert(Dsq::new_priq())
     }
 
-    fn dsq_push(&mut self, dsq_id: DsqId, task_id: TaskId, slice: Ticks, vtime: Option<Vtime>) {
+    fn dsq_push(
+        &mut self,
+        dsq_id: DsqId,
+        task_id: TaskId,
+        slice: Ticks,
+        vtime: Option<Vtime>,
+        head: bool,
+    ) {
         assert!(
             !self.task_to_dsq.contains_key(&task_id),
             "Task {task_id} already present in some DSQ"
@@ -176,9 +364,11 @@ impl KernelCtx {
         );
 
         task.allocated_timeslice = Some(slice);
+        self.dsq_scanned += self.dsq_nr_queued(dsq_id) as u64;
         let dsq = self.dsqs.get_mut(dsq_id).expect("Unknown DSQ");
 
         match dsq {
+            Dsq::Fifo { tasks } if head => tasks.push_front(task_id),
             Dsq::Fifo { tasks } => tasks.push_back(task_id),
             Dsq::Priq { tasks } => {
                 tasks.push(
@@ -192,15 +382,24 @@ impl KernelCtx {
     }
 
     pub fn dsq_push_fifo(&mut self, dsq_id: DsqId, task_id: TaskId, slice: Ticks) {
-        self.dsq_push(dsq_id, task_id, slice, None);
+        self.dsq_push(dsq_id, task_id, slice, None, false);
+    }
+
+    // As with SCX_ENQ_HEAD, e.g. for a task that lost its CPU mid-slice
+    pub fn dsq_push_fifo_head(&mut self, dsq_id: DsqId, task_id: TaskId, slice: Ticks) {
+        self.dsq_push(dsq_id, task_id, slice, None, true);
     }
 
     pub fn dsq_push_priq(&mut self, dsq_id: DsqId, task_id: TaskId, slice: Ticks, vtime: Vtime) {
-        self.dsq_push(dsq_id, task_id, slice, Some(vtime));
+        self.dsq_push(dsq_id, task_id, slice, Some(vtime), false);
     }
 
     pub fn dsq_pop(&mut self, dsq_id: DsqId) -> Option<TaskId> {
         let dsq = self.dsqs.get_mut(dsq_id)?;
+        self.dsq_scanned += match dsq {
+            Dsq::Fifo { tasks } => tasks.len(),
+            Dsq::Priq { tasks } => tasks.len(),
+        } as u64;
         let task = match dsq {
             Dsq::Fifo { tasks } => tasks.pop_front(),
             Dsq::Priq { tasks } => tasks.pop().map(|t| t.0),
@@ -208,19 +407,59 @@ impl KernelCtx {
 
         let removed = self.task_to_dsq.remove(&task);
         debug_assert!(removed.is_some(), "Task {task} missing DSQ membership");
+        self.dsq_pops.push(dsq_id);
 
         Some(task)
     }
 
-    pub fn dsq_move_to_local(&mut self, dsq_id: DsqId, cpu: CpuId) {
-        if let Some(task) = self.dsq_pop(dsq_id) {
-            self.dsq_push_fifo(
-                self.per_cpu_dsq(cpu),
-                task,
-                self.task(task)
-                    .allocated_timeslice
-                    .expect("Task on DSQ must have slice"),
-            );
+    // Takes `task_id` off whichever DSQ holds it, returning that DSQ
+    pub fn dsq_remove(&mut self, task_id: TaskId) -> Option<DsqId> {
+        let dsq_id = self.task_to_dsq.remove(&task_id)?;
+        match self.dsqs.get_mut(dsq_id).expect("Unknown DSQ") {
+            Dsq::Fifo { tasks } => tasks.retain(|&t| t != task_id),
+            Dsq::Priq { tasks } => {
+                tasks.remove(&task_id);
+            }
+        }
+        Some(dsq_id)
+    }
+
+    // Returns whether a task was moved
+    pub fn dsq_move_to_local(&mut self, dsq_id: DsqId, cpu: CpuId) -> bool {
+        let Some(task) = self.dsq_pop(dsq_id) else {
+            return false;
+        };
+        self.dsq_push_fifo(
+            self.per_cpu_dsq(cpu),
+            task,
+            self.task(task)
+                .allocated_timeslice
+                .expect("Task on DSQ must have slice"),
+        );
+        true
+    }
+
+    // Next task dsq_pop() would return
+    pub fn dsq_peek(&self, dsq_id: DsqId) -> Option<TaskId> {
+        match self.dsqs.get(dsq_id)? {
+            Dsq::Fifo { tasks } => tasks.front().copied(),
+            Dsq::Priq { tasks } => tasks.peek().map(|t| *t.0),
+        }
+    }
+
+    pub fn destroy_dsq(&mut self, dsq_id: DsqId) {
+        assert_eq!(
+            self.dsq_nr_queued(dsq_id),
+            0,
+            "Destroying non-empty DSQ {dsq_id:?}"
+        );
+        self.dsqs.remove(dsq_id);
+    }
+
+    pub fn dsq_nr_queued(&self, dsq_id: DsqId) -> usize {
+        match self.dsqs.get(dsq_id).expect("Unknown DSQ") {
+            Dsq::Fifo { tasks } => tasks.len(),
+            Dsq::Priq { tasks } => tasks.len(),
         }
     }
 
@@ -229,11 +468,11 @@ impl KernelCtx {
     }
 
     pub fn task(&self, task_id: TaskId) -> &Task {
-        &self.tasks[task_id]
+        &self.tasks[&task_id]
     }
 
     pub fn task_mut(&mut self, task_id: TaskId) -> &mut Task {
-        &mut self.tasks[task_id]
+        self.tasks.get_mut(&task_id).expect("Unknown task")
     }
 
     pub fn global_dsq(&self) -> DsqId {
@@ -245,14 +484,347 @@ impl KernelCtx {
     }
 
     pub fn cpu_is_idle(&self, cpu: CpuId) -> bool {
-        self.cpus[cpu].current.is_none()
+        let cpu = &self.cpus[cpu];
+        cpu.online && cpu.current.is_none() && cpu.stolen.is_none()
     }
 
+    pub fn cpu_is_online(&self, cpu: CpuId) -> bool {
+        self.cpus[cpu].online
+    }
+
+    // Prefers the highest-capacity idle CPU, as the kernel does on asymmetric
+    // systems, then one on a fully idle core, then the shallowest idle state
     pub fn pick_idle_cpu(&self) -> Option<CpuId> {
-        self.cpus
+        self.pick_best_cpu(|cpu| self.cpu_is_idle(cpu))
+    }
+
+    // As pick_idle_cpu(), but CPUs with tasks already dispatched to them count
+    // as claimed
+    pub fn pick_free_cpu(&self) -> Option<CpuId> {
+        self.pick_best_cpu(|cpu| self.cpu_is_free(cpu))
+    }
+
+    fn pick_best_cpu(&self, eligible: impl Fn(CpuId) -> bool) -> Option<CpuId> {
+        (0..self.cpus.len())
+            .filter(|&cpu| eligible(cpu))
+            .min_by_key(|&cpu| {
+                (
+                    std::cmp::Reverse(self.cpus[cpu].capacity),
+                    !self.core_is_idle(cpu),
+                    self.cpu_exit_latency(cpu),
+                )
+            })
+    }
+
+    // Like scx_bpf_pick_idle_cpu() with SCX_PICK_IDLE_CORE
+    pub fn pick_idle_core(&self) -> Option<CpuId> {
+        (0..self.cpus.len()).find(|&cpu| self.core_is_idle(cpu))
+    }
+
+    // `cpu` and all of its SMT siblings are free
+    pub fn core_is_idle(&self, cpu: CpuId) -> bool {
+        self.cpu_is_free(cpu)
+            && self
+                .topology
+                .smt_siblings(cpu)
+                .all(|sibling| self.cpu_is_free(sibling))
+    }
+
+    pub fn cpuperf_cap(&self, cpu: CpuId) -> u64 {
+        self.cpus[cpu].capacity
+    }
+
+    pub fn cpuperf_cur(&self, cpu: CpuId) -> u64 {
+        self.cpus[cpu].perf_cur
+    }
+
+    // Only the Schedutil governor takes the target into account
+    pub fn cpuperf_set(&mut self, cpu: CpuId, perf: u64) {
+        assert!(
+            perf <= SCX_CPUPERF_ONE,
+            "Performance target {perf} out of range"
+        );
+        self.cpus[cpu].perf_target = Some(perf);
+    }
+
+    pub(crate) fn cpu_activity(&self, cpu: CpuId) -> CpuActivity {
+        let cpu = &self.cpus[cpu];
+        let ran = cpu.current.filter(|_| cpu.online && cpu.stolen.is_none());
+        CpuActivity {
+            online: cpu.online,
+            ran,
+            busy: ran.is_some() || cpu.stolen.is_some(),
+            perf: cpu.perf_cur,
+            idle_state: cpu.idle_state,
+        }
+    }
+
+    pub fn cpu_idle_state(&self, cpu: CpuId) -> Option<usize> {
+        self.cpus[cpu].idle_state
+    }
+
+    // How long a task placed on `cpu` now would wait for it to leave idle
+    pub fn cpu_exit_latency(&self, cpu: CpuId) -> Ticks {
+        let cpu = &self.cpus[cpu];
+        cpu.idle_state
+            .map_or(0, |state| cpu.idle_states[state].exit_latency)
+    }
+
+    // Keeps `cpu` out of idle states deeper than `limit`, like a PM QoS
+    // latency constraint
+    pub fn set_idle_state_limit(&mut self, cpu: CpuId, limit: Option<usize>) {
+        let cpu = &mut self.cpus[cpu];
+        cpu.idle_state_limit = limit;
+        if let (Some(state), Some(limit)) = (cpu.idle_state, limit) {
+            cpu.idle_state = Some(state.min(limit));
+        }
+    }
+
+    pub fn cpu_is_waking(&self, cpu: CpuId) -> bool {
+        self.cpus[cpu]
+            .waking_until
+            .is_some_and(|until| until > self.now)
+    }
+
+    // Ladder governor: an idle CPU steps down to the deepest allowed state
+    // whose target residency it has already been idle for. Returns the state
+    // entered, if it changed this tick.
+    pub(crate) fn update_idle_state(&mut self, cpu: CpuId) -> Option<usize> {
+        let now = self.now;
+        let cpu = &mut self.cpus[cpu];
+        cpu.waking_until = None;
+        let idle_for = now + 1 - *cpu.idle_since.get_or_insert(now);
+        let allowed = cpu
+            .idle_state_limit
+            .map_or(cpu.idle_states.len(), |limit| limit + 1);
+        let state = cpu.idle_states[..allowed.min(cpu.idle_states.len())]
+            .iter()
+            .rposition(|state| state.target_residency <= idle_for);
+        if state == cpu.idle_state {
+            return None;
+        }
+        cpu.idle_state = state;
+        state
+    }
+
+    // Returns the idle state left, if any, and its exit latency
+    pub(crate) fn exit_idle(&mut self, cpu: CpuId) -> Option<(usize, Ticks)> {
+        let latency = self.cpu_exit_latency(cpu);
+        let cpu = &mut self.cpus[cpu];
+        cpu.idle_since = None;
+        cpu.idle_state.take().map(|state| (state, latency))
+    }
+
+    // Work a task does in a tick on `cpu`, in SCHED_CAPACITY_SCALE units,
+    // given what every CPU did during the tick. At least 1, so that a small
+    // CPU at a low performance level still makes progress.
+    pub fn cpu_throughput(&self, cpu: CpuId, activity: &[CpuActivity]) -> u64 {
+        let state = &self.cpus[cpu];
+        let work = state.capacity * state.perf_cur / SCX_CPUPERF_ONE;
+        let smt_busy = self
+            .topology
+            .smt_siblings(cpu)
+            .any(|sibling| activity[sibling].busy);
+        let work = if smt_busy {
+            work * self.topology.smt_throughput / SCHED_CAPACITY_SCALE
+        } else {
+            work
+        };
+        work.max(1)
+    }
+
+    // Returns the whole ticks of service the task made, after paying off any
+    // cache refill work
+    pub(crate) fn charge_service(&mut self, task: TaskId, work: u64) -> Ticks {
+        let task = self.task_mut(task);
+        let repaid = work.min(task.cache_debt);
+        task.cache_debt -= repaid;
+        let total = task.service_remainder + work - repaid;
+        task.service_remainder = total % SCHED_CAPACITY_SCALE;
+        let service = total / SCHED_CAPACITY_SCALE;
+        task.consumed_service = task.consumed_service.saturating_add(service);
+        service
+    }
+
+    // Samples whether each CPU was busy this tick and applies the governor's
+    // choice for the next one. Returns the CPUs whose frequency changed, with
+    // the old and new level.
+    pub(crate) fn update_cpufreq(&mut self, activity: &[CpuActivity]) -> Vec<(CpuId, u64, u64)> {
+        let governor = self.governor;
+        let mut changes = Vec::new();
+        for cpu in self.cpus.iter_mut().filter(|cpu| cpu.online) {
+            cpu.util_avg = perf::update_util_avg(cpu.util_avg, activity[cpu.id].busy);
+            let next = governor.next_perf(cpu.util_avg, cpu.perf_target, cpu.perf_min);
+            if next != cpu.perf_cur {
+                changes.push((cpu.id, cpu.perf_cur, next));
+                cpu.perf_cur = next;
+            }
+        }
+        changes
+    }
+
+    // An idle CPU with nothing queued locally
+    pub fn cpu_is_free(&self, cpu: CpuId) -> bool {
+        self.cpu_is_idle(cpu) && self.dsq_nr_queued(self.per_cpu_dsq(cpu)) == 0
+    }
+
+    pub(crate) fn take_dsq_scanned(&mut self) -> u64 {
+        std::mem::take(&mut self.dsq_scanned)
+    }
+
+    pub(crate) fn take_dsq_pops(&mut self) -> Vec<DsqId> {
+        std::mem::take(&mut self.dsq_pops)
+    }
+
+    // Returns the time left for the task in a tick on `cpu`, in
+    // SCHED_CAPACITY_SCALE units, after paying down overhead
+    pub(crate) fn pay_overhead(&mut self, cpu: CpuId) -> u64 {
+        let cpu = &mut self.cpus[cpu];
+        let paid = cpu.overhead_debt.min(SCHED_CAPACITY_SCALE);
+        cpu.overhead_debt -= paid;
+        SCHED_CAPACITY_SCALE - paid
+    }
+
+    pub(crate) fn charge_overhead(&mut self, cpu: CpuId, cost: u64) {
+        let cpu = &mut self.cpus[cpu];
+        cpu.overhead_debt += cost;
+        cpu.overhead += cost;
+    }
+
+    pub fn kick_cpu(&mut self, cpu: CpuId, flags: KickFlags) {
+        self.kicks.push((cpu, flags));
+    }
+
+    pub(crate) fn drain_kicks(&mut self) -> Vec<(CpuId, KickFlags)> {
+        std::mem::take(&mut self.kicks)
+    }
+
+    // scx_bpf_reenqueue_local(): re-enqueue every task on `cpu`'s local DSQ with
+    // SCX_ENQ_REENQ, e.g. from cpu_release() so they don't wait out the higher class
+    pub fn reenqueue_local(&mut self, cpu: CpuId) {
+        self.reenqueue_requests.push(cpu);
+    }
+
+    pub(crate) fn drain_reenqueue_requests(&mut self) -> Vec<CpuId> {
+        std::mem::take(&mut self.reenqueue_requests)
+    }
+
+    pub fn root_cgroup(&self) -> CgroupId {
+        self.root_cgroup_id
+    }
+
+    pub fn cgroup(&self, cgroup_id: CgroupId) -> &Cgroup {
+        &self.cgroups[cgroup_id]
+    }
+
+    pub fn cgroup_mut(&mut self, cgroup_id: CgroupId) -> &mut Cgroup {
+        &mut self.cgroups[cgroup_id]
+    }
+
+    pub fn cgroup_lookup(&self, path: &str) -> Option<CgroupId> {
+        let path = if path.is_empty() { "/" } else { path };
+        self.cgroups
             .iter()
-            .find(|cpu| cpu.current.is_none())
-            .map(|cpu| cpu.id)
+            .find(|(_, cgroup)| cgroup.path == path)
+            .map(|(id, _)| id)
+    }
+
+    // `cgroup_id` itself, then each ancestor up to the root
+    pub fn cgroup_ancestors(&self, cgroup_id: CgroupId) -> impl Iterator<Item = CgroupId> + '_ {
+        std::iter::successors(Some(cgroup_id), |&id| self.cgroup(id).parent)
+    }
+
+    pub fn create_cgroup(&mut self, parent: CgroupId, name: &str) -> CgroupId {
+        assert!(
+            !name.is_empty() && !name.contains('/'),
+            "Invalid cgroup name {name:?}"
+        );
+        let path = cgroup::child_path(&self.cgroup(parent).path, name);
+        assert!(
+            self.cgroup_lookup(&path).is_none(),
+            "Cgroup {path} already exists"
+        );
+
+        let id = self
+            .cgroups
+            .insert_with_key(|id| Cgroup::new(id, Some(parent), path));
+        self.cgroup_mut(parent).children.push(id);
+        id
+    }
+
+    pub fn remove_cgroup(&mut self, cgroup_id: CgroupId) {
+        let cgroup = self.cgroup(cgroup_id);
+        assert!(!cgroup.is_root(), "Cannot remove the root cgroup");
+        assert!(
+            cgroup.children.is_empty(),
+            "Cannot remove cgroup {} with children",
+            cgroup.path
+        );
+        assert!(
+            self.tasks
+                .values()
+                .all(|task| task.cgroup != cgroup_id || task.state == TaskState::Completed),
+            "Cannot remove cgroup {} with live tasks",
+            cgroup.path
+        );
+
+        let cgroup = self.cgroups.remove(cgroup_id).expect("Unknown cgroup");
+        if let Some(parent) = cgroup.parent {
+            self.cgroup_mut(parent)
+                .children
+                .retain(|&id| id != cgroup_id);
+        }
+    }
+
+    // Throttling applies to the whole subtree of a throttled group
+    pub fn cgroup_is_throttled(&self, cgroup_id: CgroupId) -> bool {
+        self.cgroup_ancestors(cgroup_id)
+            .any(|id| self.cgroup(id).throttled)
+    }
+
+    pub fn task_is_throttled(&self, task_id: TaskId) -> bool {
+        self.cgroup_is_throttled(self.task(task_id).cgroup)
+    }
+
+    // Charge CPU time against cpu.max up the hierarchy. Returns newly throttled groups.
+    pub fn charge_cgroup(&mut self, cgroup_id: CgroupId, ticks: Ticks) -> Vec<CgroupId> {
+        let ancestors: Vec<CgroupId> = self.cgroup_ancestors(cgroup_id).collect();
+        let mut throttled = Vec::new();
+        for id in ancestors {
+            let cgroup = self.cgroup_mut(id);
+            cgroup.usage += ticks;
+            cgroup.period_usage += ticks;
+            if let Some(max) = cgroup.max
+                && !cgroup.throttled
+                && cgroup.period_usage >= max.quota
+            {
+                cgroup.throttled = true;
+                throttled.push(id);
+            }
+        }
+        throttled
+    }
+
+    // Start a new cpu.max period for groups whose period boundary is `now`.
+    // Returns groups that were throttled and no longer are.
+    pub fn refresh_cgroup_bandwidth(&mut self) -> Vec<CgroupId> {
+        let now = self.now;
+        let mut unthrottled = Vec::new();
+        for (id, cgroup) in self.cgroups.iter_mut() {
+            // Groups whose limit was lifted are released immediately
+            if cgroup
+                .max
+                .is_some_and(|max| !now.is_multiple_of(max.period))
+            {
+                continue;
+            }
+            cgroup.period_usage = 0;
+            if cgroup.throttled {
+                cgroup.throttled = false;
+                unthrottled.push(id);
+            }
+        }
+        unthrottled
     }
 
     pub fn mark_runnable(&mut self, task_id: TaskId) {
@@ -277,6 +849,14 @@ impl KernelCtx {
         task.current_cpu = None;
     }
 
+    pub(crate) fn reap_task(&mut self, task_id: TaskId) {
+        let task = self.tasks.remove(&task_id).expect("Unknown task");
+        debug_assert!(
+            task.state == TaskState::Completed,
+            "Reaping task {task_id} that has not completed"
+        );
+    }
+
     pub fn mark_completed(&mut self, task_id: TaskId, completion_time: Ticks) {
         debug_assert!(
             !self.task_to_dsq.contains_key(&task_id),
@@ -284,7 +864,7 @@ impl KernelCtx {
             task_id
         );
 
-        let task = &mut self.tasks[task_id];
+        let task = self.task_mut(task_id);
         debug_assert!(
             task.state == TaskState::Running,
             "Task {task_id} must have been running before marked complete"
@@ -296,6 +876,31 @@ impl KernelCtx {
         task.completion_time = Some(completion_time);
     }
 
+    // Counts migrations and charges the cache model's penalty as `task` starts
+    // on `cpu`. Returns the CPU it moved from.
+    pub(crate) fn place_task(&mut self, cpu: CpuId, task: TaskId) -> Option<CpuId> {
+        let now = self.now;
+        let topology = &self.topology;
+        let task = self.tasks.get_mut(&task).expect("Unknown task");
+        let from = task.last_cpu.replace(cpu)?;
+        let penalty = self.cache_model.map_or(0, |model| {
+            model.penalty(topology, from, cpu, now - task.last_ran)
+        });
+        task.cache_debt += penalty;
+        task.cache_penalty += penalty;
+        if from == cpu {
+            return None;
+        }
+        task.nr_migrations += 1;
+        if !topology.share_llc(from, cpu) {
+            task.nr_llc_migrations += 1;
+        }
+        if !topology.share_node(from, cpu) {
+            task.nr_node_migrations += 1;
+        }
+        Some(from)
+    }
+
     // Return previous state (runnable, but possibly blocked if ddsp'd)
     pub fn set_running(&mut self, cpu: CpuId, task_id: TaskId) -> TaskState {
         debug_assert!(
@@ -312,10 +917,14 @@ impl KernelCtx {
         let prev_state = task_state.state;
         task_state.state = TaskState::Running;
         task_state.current_cpu = Some(cpu);
+        task_state.consumed_timeslice = 0;
         prev_state
     }
 
     pub fn clear_cpu(&mut self, cpu: CpuId) {
-        self.cpus[cpu].current = None;
+        if let Some(task) = self.cpus[cpu].current.take() {
+            let now = self.now;
+            self.task_mut(task).last_ran = now;
+        }
     }
 }
@@ -0,0 +1,87 @@
+use super::{perf::SCHED_CAPACITY_SCALE, state::CpuId};
+
+// Per-thread throughput while the SMT sibling is also busy, in
+// SCHED_CAPACITY_SCALE units: both threads together do ~1.25x one thread
+pub const SMT_THROUGHPUT_DFL: u64 = 640;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuTopo {
+    pub core: usize,
+    pub llc: usize,
+    pub node: usize,
+    // Relative to the fastest CPU, in SCHED_CAPACITY_SCALE units
+    pub capacity: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topology {
+    pub cpus: Vec<CpuTopo>,
+    pub smt_throughput: u64,
+}
+
+impl Topology {
+    // One core per CPU, sharing a single LLC and node
+    pub fn flat(num_cpus: usize) -> Self {
+        Self::with_capacities(&vec![SCHED_CAPACITY_SCALE; num_cpus])
+    }
+
+    pub fn with_capacities(capacities: &[u64]) -> Self {
+        Self {
+            cpus: capacities
+                .iter()
+                .enumerate()
+                .map(|(id, &capacity)| CpuTopo {
+                    core: id,
+                    llc: 0,
+                    node: 0,
+                    capacity,
+                })
+                .collect(),
+            smt_throughput: SMT_THROUGHPUT_DFL,
+        }
+    }
+
+    // A symmetric machine. CPUs are numbered so that hardware threads of a
+    // core, cores of an LLC, and LLCs of a node are adjacent.
+    pub fn new(
+        nodes: usize,
+        llcs_per_node: usize,
+        cores_per_llc: usize,
+        threads_per_core: usize,
+    ) -> Self {
+        let num_cpus = nodes * llcs_per_node * cores_per_llc * threads_per_core;
+        Self {
+            cpus: (0..num_cpus)
+                .map(|id| {
+                    let core = id / threads_per_core;
+                    let llc = core / cores_per_llc;
+                    CpuTopo {
+                        core,
+                        llc,
+                        node: llc / llcs_per_node,
+                        capacity: SCHED_CAPACITY_SCALE,
+                    }
+                })
+                .collect(),
+            smt_throughput: SMT_THROUGHPUT_DFL,
+        }
+    }
+
+    pub fn num_cpus(&self) -> usize {
+        self.cpus.len()
+    }
+
+    // Other hardware threads of `cpu`'s core
+    pub fn smt_siblings(&self, cpu: CpuId) -> impl Iterator<Item = CpuId> + '_ {
+        let core = self.cpus[cpu].core;
+        (0..self.cpus.len()).filter(move |&other| other != cpu && self.cpus[other].core == core)
+    }
+
+    pub fn share_llc(&self, a: CpuId, b: CpuId) -> bool {
+        self.cpus[a].llc == self.cpus[b].llc
+    }
+
+    pub fn share_node(&self, a: CpuId, b: CpuId) -> bool {
+        self.cpus[a].node == self.cpus[b].node
+    }
+}
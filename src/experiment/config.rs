@@ -0,0 +1,306 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    core::{
+        Ticks,
+        prio::{MAX_NICE, MIN_NICE},
+    },
+    scheduler::layered::{LayeredConfig, LayeredConfigError},
+    sim::{Arrivals, Job, JobClass, ServiceTime, Workload},
+};
+
+// An experiment file, in TOML or (with a .json extension) JSON
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentConfig {
+    #[serde(default)]
+    pub seed: u64,
+    // Jobs arrive during the first `horizon` ticks
+    pub horizon: Ticks,
+    // Stop here even if jobs are still running
+    #[serde(default)]
+    pub max_ticks: Option<Ticks>,
+    pub cpus: CpuSpec,
+    pub scheduler: SchedulerSpec,
+    pub workloads: Vec<WorkloadSpec>,
+    #[serde(default = "default_metrics")]
+    pub metrics: Vec<Metric>,
+}
+
+// Either a flat `count` of CPUs or a `topology`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpuSpec {
+    #[serde(default)]
+    pub count: Option<usize>,
+    #[serde(default)]
+    pub topology: Option<TopologySpec>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TopologySpec {
+    pub nodes: usize,
+    pub llcs_per_node: usize,
+    pub cores_per_llc: usize,
+    pub threads_per_core: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SchedulerSpec {
+    Fifo,
+    Priq,
+    Edf,
+    Flatcg,
+    Layered { layers: LayeredConfig },
+}
+
+// Workloads are generated independently, seeded from the experiment's seed,
+// and merged in arrival order
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkloadSpec {
+    pub arrivals: Arrivals,
+    pub classes: Vec<ClassSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClassSpec {
+    #[serde(default = "default_share")]
+    pub share: f64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub nice: Option<i32>,
+    #[serde(default)]
+    pub weight: Option<u64>,
+    #[serde(default)]
+    pub cgroup: String,
+    #[serde(default)]
+    pub deadline: Option<Ticks>,
+    pub service: ServiceTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    // (completion - arrival) / run_time
+    Slowdown,
+    // completion - arrival
+    ResponseTime,
+    // first run - arrival
+    WaitTime,
+    DeadlineMisses,
+    Migrations,
+    // Tick at which the last job completed
+    Makespan,
+    // CPU time of each cgroup against a hierarchical fluid model
+    CgroupFairness,
+}
+
+fn default_share() -> f64 {
+    1.0
+}
+
+fn default_metrics() -> Vec<Metric> {
+    vec![
+        Metric::Slowdown,
+        Metric::ResponseTime,
+        Metric::DeadlineMisses,
+        Metric::Makespan,
+    ]
+}
+
+impl CpuSpec {
+    pub fn num_cpus(&self) -> usize {
+        match (self.count, self.topology) {
+            (Some(count), _) => count,
+            (None, Some(topo)) => {
+                topo.nodes * topo.llcs_per_node * topo.cores_per_llc * topo.threads_per_core
+            }
+            (None, None) => 0,
+        }
+    }
+}
+
+impl WorkloadSpec {
+    pub fn workload(&self, seed: u64, horizon: Ticks) -> Workload {
+        Workload {
+            seed,
+            horizon: Some(horizon),
+            arrivals: self.arrivals.clone(),
+            classes: self.classes.iter().map(ClassSpec::job_class).collect(),
+        }
+    }
+}
+
+impl ClassSpec {
+    pub fn job_class(&self) -> JobClass {
+        let default = Job::default();
+        JobClass {
+            share: self.share,
+            job: Job {
+                name: self.name.clone(),
+                tags: self.tags.clone(),
+                nice: self.nice,
+                weight: self.weight.unwrap_or(default.weight),
+                cgroup: self.cgroup.clone(),
+                deadline: self.deadline,
+                ..default
+            },
+            service: self.service.clone(),
+        }
+    }
+}
+
+impl ExperimentConfig {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ExperimentError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ExperimentError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, ExperimentError> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    pub fn validate(&self) -> Result<(), ExperimentError> {
+        let invalid = |msg: String| Err(ExperimentError::Invalid(msg));
+
+        if self.horizon == 0 {
+            return invalid("horizon must be nonzero".to_string());
+        }
+        if self.max_ticks == Some(0) {
+            return invalid("max_ticks must be nonzero".to_string());
+        }
+
+        match (self.cpus.count, self.cpus.topology) {
+            (Some(_), Some(_)) => {
+                return invalid("cpus: set either count or topology, not both".to_string());
+            }
+            (None, None) => return invalid("cpus: set count or topology".to_string()),
+            (Some(0), None) => return invalid("cpus.count must be nonzero".to_string()),
+            (None, Some(topo)) => {
+                if topo.nodes == 0
+                    || topo.llcs_per_node == 0
+                    || topo.cores_per_llc == 0
+                    || topo.threads_per_core == 0
+                {
+                    return invalid("cpus.topology: every level must be nonzero".to_string());
+                }
+            }
+            (Some(_), None) => {}
+        }
+
+        if let SchedulerSpec::Layered { layers } = &self.scheduler {
+            layers
+                .validate(self.cpus.num_cpus())
+                .map_err(|err| match err {
+                    LayeredConfigError::Invalid(msg) => {
+                        ExperimentError::Invalid(format!("scheduler.layers: {msg}"))
+                    }
+                    err => ExperimentError::Invalid(format!("scheduler.layers: {err}")),
+                })?;
+        }
+
+        if self.workloads.is_empty() {
+            return invalid("at least one workload is required".to_string());
+        }
+        for (i, workload) in self.workloads.iter().enumerate() {
+            let at = format!("workloads[{i}]");
+            workload
+                .workload(self.seed, self.horizon)
+                .validate()
+                .map_err(|msg| ExperimentError::Invalid(format!("{at}: {msg}")))?;
+            for (j, class) in workload.classes.iter().enumerate() {
+                let at = format!("{at}.classes[{j}]");
+                if class
+                    .nice
+                    .is_some_and(|nice| !(MIN_NICE..=MAX_NICE).contains(&nice))
+                {
+                    return invalid(format!("{at}.nice must be within {MIN_NICE}..={MAX_NICE}"));
+                }
+                if class.nice.is_some() && class.weight.is_some() {
+                    return invalid(format!("{at}: set either nice or weight, not both"));
+                }
+                if class.weight == Some(0) {
+                    return invalid(format!("{at}.weight must be nonzero"));
+                }
+                if class.deadline == Some(0) {
+                    return invalid(format!("{at}.deadline must be nonzero"));
+                }
+                if !class.cgroup.is_empty() && !class.cgroup.starts_with('/') {
+                    return invalid(format!("{at}.cgroup must be an absolute path"));
+                }
+            }
+        }
+
+        if self.metrics.is_empty() {
+            return invalid("at least one metric is required".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ExperimentError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ExperimentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read experiment: {err}"),
+            Self::Json(err) => write!(f, "failed to parse experiment: {err}"),
+            Self::Toml(err) => write!(f, "failed to parse experiment: {err}"),
+            Self::Invalid(msg) => write!(f, "invalid experiment: {msg}"),
+        }
+    }
+}
+
+impl Error for ExperimentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+            Self::Toml(err) => Some(err),
+            Self::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ExperimentError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ExperimentError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<toml::de::Error> for ExperimentError {
+    fn from(err: toml::de::Error) -> Self {
+        Self::Toml(err)
+    }
+}
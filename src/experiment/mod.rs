@@ -0,0 +1,242 @@
+pub mod config;
+
+use std::fmt;
+
+pub use config::{
+    ClassSpec, CpuSpec, ExperimentConfig, ExperimentError, Metric, SchedulerSpec, TopologySpec,
+    WorkloadSpec,
+};
+
+use crate::{
+    core::{KernelCtx, Ticks, Topology, driver::SchedCore},
+    scheduler::{
+        EdfScheduler, FifoScheduler, FlatcgScheduler, LayeredScheduler, PriqScheduler, Scheduler,
+    },
+    sim::{CgroupFairness, CgroupShare, Job, JobInstance, Sim},
+};
+
+// Outcome of an experiment, formatted according to its metrics
+#[derive(Debug)]
+pub struct Report {
+    pub metrics: Vec<Metric>,
+    // Completed jobs, in order of completion
+    pub jobs: Vec<JobInstance>,
+    pub incomplete: bool,
+    pub ticks: Ticks,
+    // Set with Metric::CgroupFairness
+    pub cgroups: Vec<CgroupShare>,
+}
+
+impl ExperimentConfig {
+    pub fn run(&self) -> Result<Report, ExperimentError> {
+        self.validate()?;
+        let report = match &self.scheduler {
+            SchedulerSpec::Fifo => self.run_with(FifoScheduler::init),
+            SchedulerSpec::Priq => self.run_with(PriqScheduler::init),
+            SchedulerSpec::Edf => self.run_with(EdfScheduler::init),
+            SchedulerSpec::Flatcg => self.run_with(FlatcgScheduler::init),
+            SchedulerSpec::Layered { layers } => self.run_with(|ctx| {
+                LayeredScheduler::new(ctx, layers.clone()).expect("validated scheduler.layers")
+            }),
+        };
+        Ok(report)
+    }
+
+    // Every workload's jobs, merged in arrival order and renumbered
+    pub fn jobs(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self
+            .workloads
+            .iter()
+            .enumerate()
+            .flat_map(|(i, workload)| {
+                workload
+                    .workload(self.seed.wrapping_add(i as u64), self.horizon)
+                    .generate()
+            })
+            .collect();
+        jobs.sort_by_key(|job| job.arrival_time);
+        for (id, job) in jobs.iter_mut().enumerate() {
+            job.id = id as u64;
+        }
+        jobs
+    }
+
+    fn run_with<S: Scheduler>(&self, init: impl FnOnce(&mut KernelCtx) -> S) -> Report {
+        let ctx = match (self.cpus.count, self.cpus.topology) {
+            (Some(count), _) => KernelCtx::new(count),
+            (None, Some(topo)) => KernelCtx::with_topology(Topology::new(
+                topo.nodes,
+                topo.llcs_per_node,
+                topo.cores_per_llc,
+                topo.threads_per_core,
+            )),
+            (None, None) => unreachable!("validated CPU spec"),
+        };
+        let mut sim = Sim::with_core(self.jobs(), SchedCore::with_ctx(ctx, init));
+        let mut fairness = self
+            .metrics
+            .contains(&Metric::CgroupFairness)
+            .then(CgroupFairness::new);
+        if let Some(fairness) = &mut fairness {
+            fairness.record(&sim.core.ctx);
+        }
+
+        while !sim.all_jobs_completed() {
+            if self.max_ticks.is_some_and(|max| sim.core.now() >= max) {
+                break;
+            }
+            sim.step();
+            if let Some(fairness) = &mut fairness {
+                fairness.record(&sim.core.ctx);
+            }
+        }
+
+        Report {
+            metrics: self.metrics.clone(),
+            incomplete: !sim.all_jobs_completed(),
+            ticks: sim.core.now(),
+            jobs: sim.jobs,
+            cgroups: fairness.map_or_else(Vec::new, |fairness| fairness.shares()),
+        }
+    }
+}
+
+struct Summary {
+    mean: f64,
+    p50: f64,
+    p99: f64,
+    max: f64,
+}
+
+impl Summary {
+    fn new(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let at = |q: f64| values[((values.len() - 1) as f64 * q).round() as usize];
+        Some(Self {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            p50: at(0.5),
+            p99: at(0.99),
+            max: values[values.len() - 1],
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mean {:.3}, p50 {:.3}, p99 {:.3}, max {:.3}",
+            self.mean, self.p50, self.p99, self.max
+        )
+    }
+}
+
+impl Report {
+    fn write_group(&self, f: &mut fmt::Formatter<'_>, jobs: &[&JobInstance]) -> fmt::Result {
+        let summary =
+            |f: &mut fmt::Formatter<'_>, name: &str, values: Vec<f64>| match Summary::new(values) {
+                Some(summary) => writeln!(f, "  {name}: {summary}"),
+                None => writeln!(f, "  {name}: no jobs"),
+            };
+        for metric in &self.metrics {
+            match metric {
+                Metric::Slowdown => summary(
+                    f,
+                    "slowdown",
+                    jobs.iter()
+                        .map(|job| response_time(job) as f64 / job.job.run_time as f64)
+                        .collect(),
+                )?,
+                Metric::ResponseTime => summary(
+                    f,
+                    "response time",
+                    jobs.iter().map(|job| response_time(job) as f64).collect(),
+                )?,
+                Metric::WaitTime => summary(
+                    f,
+                    "wait time",
+                    jobs.iter()
+                        .filter_map(|job| {
+                            job.start_time
+                                .map(|start| (start - job.job.arrival_time) as f64)
+                        })
+                        .collect(),
+                )?,
+                Metric::Migrations => summary(
+                    f,
+                    "migrations",
+                    jobs.iter().map(|job| job.migrations as f64).collect(),
+                )?,
+                Metric::DeadlineMisses => {
+                    let misses = jobs.iter().filter(|job| job.missed_deadline()).count();
+                    writeln!(f, "  deadline misses: {misses}")?
+                }
+                Metric::Makespan => {
+                    let makespan = jobs.iter().filter_map(|job| job.completion_time).max();
+                    writeln!(f, "  makespan: {}", makespan.unwrap_or(0))?
+                }
+                // Per cgroup rather than per job, so written once for the whole run
+                Metric::CgroupFairness => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn response_time(job: &JobInstance) -> Ticks {
+    job.completion_time.expect("Reported job has not completed") - job.job.arrival_time
+}
+
+// All jobs, then each named class
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} jobs completed in {} ticks",
+            self.jobs.len(),
+            self.ticks
+        )?;
+        if self.incomplete {
+            writeln!(f, "stopped at max_ticks with jobs still running")?;
+        }
+
+        writeln!(f, "all:")?;
+        self.write_group(f, &self.jobs.iter().collect::<Vec<_>>())?;
+
+        let mut names: Vec<&str> = self
+            .jobs
+            .iter()
+            .map(|job| job.job.name.as_str())
+            .filter(|name| !name.is_empty())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        for name in names {
+            writeln!(f, "{name}:")?;
+            let jobs: Vec<&JobInstance> = self
+                .jobs
+                .iter()
+                .filter(|job| job.job.name == name)
+                .collect();
+            self.write_group(f, &jobs)?;
+        }
+
+        if !self.cgroups.is_empty() {
+            writeln!(f, "cgroups (CPU time, hierarchical ideal, error):")?;
+            for share in &self.cgroups {
+                writeln!(
+                    f,
+                    "  {}: {}, {:.1}, {:+.1}",
+                    share.path,
+                    share.actual,
+                    share.ideal,
+                    share.error()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
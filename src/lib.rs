@@ -1,6 +1,8 @@
 pub mod core;
+pub mod experiment;
 pub mod scheduler;
 pub mod sim;
+pub mod trace;
 
 pub use core::SchedCoreEvent;
 pub use scheduler::Scheduler;
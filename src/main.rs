@@ -1,31 +1,51 @@
 use average::Estimate;
-use rand::prelude::*;
-use rand_distr::Poisson;
 use scx_model::{
     Job, SchedCoreEvent, Sim,
-    core::Ticks,
-    scheduler::{FifoScheduler, PriqScheduler},
-    sim::JobId,
+    experiment::ExperimentConfig,
+    scheduler::PriqScheduler,
+    sim::{Arrivals, JobClass, ServiceTime, Workload},
 };
-use std::{cmp, ops::Range};
+use std::{cmp, env, process};
 
 fn main() {
-    let job_cfg = JobGenerator {
+    // With an experiment file, run it instead of the built-in workload
+    if let Some(path) = env::args().nth(1) {
+        let report = ExperimentConfig::from_file(&path).and_then(|config| config.run());
+        match report {
+            Ok(report) => print!("{report}"),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let heavy_nice = -20;
+    let normal_nice = 0;
+    // Simulate bimodal job length
+    let service = ServiceTime::Mixture(vec![
+        (0.2, ServiceTime::Uniform { min: 1, max: 2 }),
+        (0.8, ServiceTime::Uniform { min: 6, max: 9 }),
+    ]);
+    let class = |share, nice| JobClass {
+        share,
+        job: Job {
+            nice: Some(nice),
+            ..Default::default()
+        },
+        service: service.clone(),
+    };
+    let workload = Workload {
         seed: 0,
         // Latest job arrival
-        horizon: 1000,
+        horizon: Some(1000),
         // E[jobs/timestep]
-        lambda: 1.0,
-        // Proportion of heavy-weight jobs
-        p_weighted: 0.3,
-        heavy_weight: 10000,
-        normal_weight: 100,
-        // Simulate bimodal job length
-        p_hit: 0.2,
-        cache_hit_range: 1..3,
-        cache_miss_range: 6..10,
+        arrivals: Arrivals::Poisson { rate: 1.0 },
+        // 30% heavy-weight jobs
+        classes: vec![class(0.3, heavy_nice), class(0.7, normal_nice)],
     };
-    let jobs = job_cfg.generate();
+    let jobs = workload.generate();
     let num_cpus = 8;
     let mut sim = Sim::<PriqScheduler>::new(jobs, num_cpus);
 
@@ -39,10 +59,9 @@ fn main() {
         for event in events {
             println!("t={} {:?}", now, event);
 
-            match event {
-                SchedCoreEvent::CpuIdle { cpu } => got_idle[cpu] = true,
-                _ => {}
-            };
+            if let SchedCoreEvent::CpuIdle { cpu } = event {
+                got_idle[cpu] = true;
+            }
         }
 
         for cpu in 0..num_cpus {
@@ -56,11 +75,11 @@ fn main() {
     }
 
     let heavy_slowdowns = sim.jobs_filter_map(
-        |j| j.job.weight == job_cfg.heavy_weight,
+        |j| j.job.nice == Some(heavy_nice),
         |j| (j.completion_time.unwrap() as f64 - j.job.arrival_time as f64) / j.job.run_time as f64,
     );
     let normal_slowdowns = sim.jobs_filter_map(
-        |j| j.job.weight == job_cfg.normal_weight,
+        |j| j.job.nice == Some(normal_nice),
         |j| (j.completion_time.unwrap() as f64 - j.job.arrival_time as f64) / j.job.run_time as f64,
     );
 
@@ -70,66 +89,6 @@ fn main() {
 }
 
 /// HELPERS ///
-#[derive(Debug)]
-struct JobGenerator {
-    seed: u64,
-    horizon: Ticks,
-    lambda: f64,
-    p_weighted: f64,
-    heavy_weight: u64,
-    normal_weight: u64,
-    p_hit: f64,
-    cache_hit_range: Range<Ticks>,
-    cache_miss_range: Range<Ticks>,
-}
-
-impl JobGenerator {
-    fn generate(&self) -> Vec<Job> {
-        let Self {
-            seed,
-            horizon,
-            lambda,
-            p_weighted,
-            heavy_weight,
-            normal_weight,
-            p_hit,
-            ..
-        } = *self;
-
-        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
-        let poisson = Poisson::new(lambda).expect("invalid lambda for Poisson");
-        let mut jobs = Vec::with_capacity((lambda * horizon as f64) as usize);
-        let mut next_id: JobId = 0;
-
-        for t in 0..horizon {
-            let arrivals = poisson.sample(&mut rng) as u64;
-            for _ in 0..arrivals {
-                let is_hit = rng.random_bool(p_hit);
-                let run_time = rng.random_range(if is_hit {
-                    self.cache_hit_range.clone()
-                } else {
-                    self.cache_miss_range.clone()
-                });
-                let weight = if rng.random_bool(p_weighted) {
-                    heavy_weight
-                } else {
-                    normal_weight
-                };
-
-                jobs.push(Job {
-                    id: next_id,
-                    arrival_time: t,
-                    run_time,
-                    weight,
-                });
-                next_id += 1;
-            }
-        }
-
-        jobs
-    }
-}
-
 fn avg(iter: impl Iterator<Item = f64>) -> f64 {
     iter.collect::<average::Mean>().estimate()
 }
@@ -0,0 +1,54 @@
+use super::{CpuId, EnqueueFlags, KernelCtx, Scheduler, SelectCpuDecision, TaskId, WakeFlags};
+use crate::{
+    core::{DsqId, Vtime},
+    scheduler::SCX_SLICE_DFL,
+};
+
+// Earliest-deadline-first. Tasks with a deadline are kept in a PRIQ DSQ keyed
+// on absolute deadline; tasks without one only run when no deadline task is
+// waiting. Deadlines are re-evaluated at every slice expiry.
+pub struct EdfScheduler {
+    deadline_priq: DsqId,
+    best_effort_fifo: DsqId,
+}
+
+impl Scheduler for EdfScheduler {
+    fn init(ctx: &mut KernelCtx) -> Self {
+        Self {
+            deadline_priq: ctx.create_dsq_priq(),
+            best_effort_fifo: ctx.create_dsq_fifo(),
+        }
+    }
+
+    // Always go through enqueue() so that simultaneous wakeups are ordered by deadline
+    fn select_cpu(
+        &mut self,
+        _ctx: &mut KernelCtx,
+        _task: TaskId,
+        _wakeup_cpu: CpuId,
+        _wake_flags: WakeFlags,
+    ) -> SelectCpuDecision {
+        SelectCpuDecision::EnqueueOnDefault
+    }
+
+    fn enqueue(
+        &mut self,
+        ctx: &mut KernelCtx,
+        task: TaskId,
+        _flags: EnqueueFlags,
+        _prev_cpu: CpuId,
+    ) {
+        match ctx.task(task).deadline {
+            Some(deadline) => {
+                ctx.dsq_push_priq(self.deadline_priq, task, SCX_SLICE_DFL, Vtime(deadline))
+            }
+            None => ctx.dsq_push_fifo(self.best_effort_fifo, task, SCX_SLICE_DFL),
+        }
+    }
+
+    fn dispatch(&mut self, ctx: &mut KernelCtx, cpu: CpuId) {
+        if !ctx.dsq_move_to_local(self.deadline_priq, cpu) {
+            ctx.dsq_move_to_local(self.best_effort_fifo, cpu);
+        }
+    }
+}
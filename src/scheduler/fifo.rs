@@ -1,4 +1,4 @@
-use super::{CpuId, EnqueueFlags, KernelCtx, Scheduler, SelectCpuDecision, TaskId};
+use super::{CpuId, EnqueueFlags, KernelCtx, Scheduler, SelectCpuDecision, TaskId, WakeFlags};
 
 pub struct FifoScheduler;
 
@@ -12,6 +12,7 @@ impl Scheduler for FifoScheduler {
         _ctx: &mut KernelCtx,
         _task: TaskId,
         _wakeup_cpu: CpuId,
+        _wake_flags: WakeFlags,
     ) -> SelectCpuDecision {
         SelectCpuDecision::EnqueueOnDefault
     }
@@ -0,0 +1,310 @@
+use std::cmp;
+
+use keyed_priority_queue::KeyedPriorityQueue;
+use rustc_hash::FxHashMap;
+
+use super::{CpuId, EnqueueFlags, KernelCtx, Scheduler, SelectCpuDecision, TaskId, WakeFlags};
+use crate::{
+    core::{CgroupId, DsqId, TaskState, Vtime},
+    scheduler::{SCX_ENQ_REENQ, SCX_SLICE_DFL, VTIME_PER_TICK, scale_by_task_weight_inverse},
+};
+
+pub const FCG_HWEIGHT_ONE: u64 = 1 << 16;
+
+// Port of scx_flatcg. The hierarchy is flattened by compounding each cgroup's
+// share of its parent (weight / sum of active sibling weights) into a
+// hierarchical weight. Every cgroup has a vtime-ordered DSQ of its tasks, and
+// dispatch() serves the cgroup with the lowest cgroup vtime, which advances
+// inversely to hweight.
+pub struct FlatcgScheduler {
+    // Tasks in the root cgroup bypass cgroup scheduling, as in scx_flatcg
+    fallback_dsq: DsqId,
+    cgroups: FxHashMap<CgroupId, CgroupCtx>,
+    // Cgroups with queued tasks, keyed on cgroup vtime
+    cgroup_queue: KeyedPriorityQueue<CgroupId, Vtime>,
+    cvtime_now: u64,
+    // Cgroup vtime is charged for a full slice at dispatch, then settled
+    // against actual usage in stopping(). Kept with the cgroup charged and the
+    // vtime charged, so a dispatch that never ran can be refunded exactly.
+    prepaid: FxHashMap<TaskId, (CgroupId, u64)>,
+}
+
+struct CgroupCtx {
+    dsq: DsqId,
+    // Copy of cpu.weight, so that cgroup_set_weight() can adjust the parent's sum
+    weight: u64,
+    cvtime: u64,
+    tvtime_now: u64,
+    nr_runnable: u64,
+    nr_active_children: u64,
+    child_weight_sum: u64,
+}
+
+impl CgroupCtx {
+    fn new(dsq: DsqId, weight: u64, cvtime: u64) -> Self {
+        Self {
+            dsq,
+            weight,
+            cvtime,
+            tvtime_now: SCX_SLICE_DFL * VTIME_PER_TICK,
+            nr_runnable: 0,
+            nr_active_children: 0,
+            child_weight_sum: 0,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.nr_runnable > 0 || self.nr_active_children > 0
+    }
+}
+
+impl FlatcgScheduler {
+    // Share of the whole machine, in units of FCG_HWEIGHT_ONE. Inactive cgroups
+    // report the share they would get if they became active.
+    pub fn hweight(&self, ctx: &KernelCtx, cgroup: CgroupId) -> u64 {
+        let mut hweight = FCG_HWEIGHT_ONE;
+        for id in ctx.cgroup_ancestors(cgroup) {
+            let Some(parent) = ctx.cgroup(id).parent else {
+                break;
+            };
+            let cgc = &self.cgroups[&id];
+            let sum = self.cgroups[&parent].child_weight_sum
+                + if cgc.is_active() { 0 } else { cgc.weight };
+            hweight = hweight * cgc.weight / sum;
+        }
+        cmp::max(hweight, 1)
+    }
+
+    fn cgroup_ctx(&mut self, cgroup: CgroupId) -> &mut CgroupCtx {
+        self.cgroups
+            .get_mut(&cgroup)
+            .expect("cgroup_init() not called for cgroup")
+    }
+
+    // Takes back cgroup vtime charged at dispatch, unless the cgroup is gone
+    fn refund(&mut self, (cgroup, charge): (CgroupId, u64)) {
+        if let Some(cgc) = self.cgroups.get_mut(&cgroup) {
+            cgc.cvtime = cgc.cvtime.saturating_sub(charge);
+        }
+    }
+
+    fn update_runnable(&mut self, ctx: &KernelCtx, cgroup: CgroupId, runnable: bool) {
+        let cvtime_floor = self
+            .cvtime_now
+            .saturating_sub(SCX_SLICE_DFL * FCG_HWEIGHT_ONE);
+        let cgc = self.cgroup_ctx(cgroup);
+        let was_active = cgc.is_active();
+        if runnable {
+            cgc.nr_runnable += 1;
+        } else {
+            cgc.nr_runnable -= 1;
+        }
+        if was_active == cgc.is_active() {
+            return;
+        }
+        // Idle cgroups must not bank vtime credit
+        if runnable {
+            cgc.cvtime = cmp::max(cgc.cvtime, cvtime_floor);
+        }
+
+        // Propagate (de)activation until an ancestor's state is unchanged
+        let mut id = cgroup;
+        while let Some(parent) = ctx.cgroup(id).parent {
+            let weight = self.cgroups[&id].weight;
+            let pcgc = self.cgroup_ctx(parent);
+            let was_active = pcgc.is_active();
+            if runnable {
+                pcgc.nr_active_children += 1;
+                pcgc.child_weight_sum += weight;
+            } else {
+                pcgc.nr_active_children -= 1;
+                pcgc.child_weight_sum -= weight;
+            }
+            if was_active == pcgc.is_active() {
+                break;
+            }
+            id = parent;
+        }
+    }
+}
+
+impl Scheduler for FlatcgScheduler {
+    fn init(ctx: &mut KernelCtx) -> Self {
+        let fallback_dsq = ctx.create_dsq_fifo();
+        let root = ctx.root_cgroup();
+        let mut cgroups = FxHashMap::default();
+        cgroups.insert(
+            root,
+            CgroupCtx::new(fallback_dsq, ctx.cgroup(root).weight, 0),
+        );
+
+        Self {
+            fallback_dsq,
+            cgroups,
+            cgroup_queue: KeyedPriorityQueue::new(),
+            cvtime_now: 0,
+            prepaid: FxHashMap::default(),
+        }
+    }
+
+    fn select_cpu(
+        &mut self,
+        ctx: &mut KernelCtx,
+        _task: TaskId,
+        wakeup_cpu: CpuId,
+        _wake_flags: WakeFlags,
+    ) -> SelectCpuDecision {
+        let free_cpu = if ctx.cpu_is_free(wakeup_cpu) {
+            Some(wakeup_cpu)
+        } else {
+            (0..ctx.cpus.len()).find(|&cpu| ctx.cpu_is_free(cpu))
+        };
+        match free_cpu {
+            Some(cpu) => SelectCpuDecision::DirectDispatch(cpu, SCX_SLICE_DFL),
+            None => SelectCpuDecision::EnqueueOnDefault,
+        }
+    }
+
+    fn enqueue(
+        &mut self,
+        ctx: &mut KernelCtx,
+        task: TaskId,
+        _flags: EnqueueFlags,
+        _prev_cpu: CpuId,
+    ) {
+        let cgroup = ctx.task(task).cgroup;
+        if cgroup == ctx.root_cgroup() {
+            ctx.dsq_push_fifo(self.fallback_dsq, task, SCX_SLICE_DFL);
+            return;
+        }
+
+        let cgc = &self.cgroups[&cgroup];
+        let vtime = cmp::max(
+            ctx.task(task).vtime,
+            cgc.tvtime_now
+                .saturating_sub(SCX_SLICE_DFL * VTIME_PER_TICK),
+        );
+        ctx.dsq_push_priq(cgc.dsq, task, SCX_SLICE_DFL, Vtime(vtime));
+        if self.cgroup_queue.get_priority(&cgroup).is_none() {
+            self.cgroup_queue.push(cgroup, Vtime(cgc.cvtime));
+        }
+    }
+
+    fn dispatch(&mut self, ctx: &mut KernelCtx, cpu: CpuId) {
+        if ctx.dsq_move_to_local(self.fallback_dsq, cpu) {
+            return;
+        }
+
+        while let Some((cgroup, _)) = self.cgroup_queue.pop() {
+            let Some(cgc) = self.cgroups.get(&cgroup) else {
+                continue;
+            };
+            let Some(task) = ctx.dsq_peek(cgc.dsq) else {
+                continue;
+            };
+            ctx.dsq_move_to_local(cgc.dsq, cpu);
+
+            let slice = ctx
+                .task(task)
+                .allocated_timeslice
+                .expect("Task on DSQ must have slice");
+            let hweight = self.hweight(ctx, cgroup);
+            let charge = slice * FCG_HWEIGHT_ONE / hweight;
+            // A task held back by throttling after its last dispatch never ran
+            // that stint, so its prepayment is refunded rather than settled
+            if let Some(stale) = self.prepaid.insert(task, (cgroup, charge)) {
+                self.refund(stale);
+            }
+
+            let cgc = self.cgroup_ctx(cgroup);
+            let cvtime = cgc.cvtime;
+            cgc.cvtime += charge;
+            let (dsq, next_cvtime) = (cgc.dsq, cgc.cvtime);
+            self.cvtime_now = cmp::max(self.cvtime_now, cvtime);
+            if ctx.dsq_nr_queued(dsq) > 0 {
+                self.cgroup_queue.push(cgroup, Vtime(next_cvtime));
+            }
+            return;
+        }
+    }
+
+    fn runnable(&mut self, ctx: &mut KernelCtx, task: TaskId, _flags: EnqueueFlags) {
+        self.update_runnable(ctx, ctx.task(task).cgroup, true);
+    }
+
+    fn quiescent(&mut self, ctx: &mut KernelCtx, task: TaskId) {
+        self.update_runnable(ctx, ctx.task(task).cgroup, false);
+    }
+
+    fn running(&mut self, ctx: &mut KernelCtx, task: TaskId) {
+        let vtime = ctx.task(task).vtime;
+        let cgc = self.cgroup_ctx(ctx.task(task).cgroup);
+        cgc.tvtime_now = cmp::max(cgc.tvtime_now, vtime);
+    }
+
+    fn stopping(&mut self, ctx: &mut KernelCtx, task: TaskId, _runnable: bool) {
+        let (cgroup, used) = {
+            let task = ctx.task_mut(task);
+            task.vtime += scale_by_task_weight_inverse(task, task.consumed_timeslice);
+            (task.cgroup, task.consumed_timeslice)
+        };
+        if let Some(prepaid) = self.prepaid.remove(&task) {
+            self.refund(prepaid);
+        }
+        if cgroup == ctx.root_cgroup() {
+            return;
+        }
+
+        let hweight = self.hweight(ctx, cgroup);
+        self.cgroup_ctx(cgroup).cvtime += used * FCG_HWEIGHT_ONE / hweight;
+    }
+
+    fn enable(&mut self, ctx: &mut KernelCtx, task: TaskId) {
+        let tvtime_now = self.cgroup_ctx(ctx.task(task).cgroup).tvtime_now;
+        ctx.task_mut(task).vtime = tvtime_now;
+    }
+
+    fn cgroup_init(&mut self, ctx: &mut KernelCtx, cgroup: CgroupId) {
+        let dsq = ctx.create_dsq_priq();
+        let weight = ctx.cgroup(cgroup).weight;
+        self.cgroups
+            .insert(cgroup, CgroupCtx::new(dsq, weight, self.cvtime_now));
+    }
+
+    fn cgroup_exit(&mut self, ctx: &mut KernelCtx, cgroup: CgroupId) {
+        let cgc = self
+            .cgroups
+            .remove(&cgroup)
+            .expect("cgroup_init() not called for cgroup");
+        debug_assert!(!cgc.is_active(), "Exiting cgroup is still active");
+        self.cgroup_queue.remove(&cgroup);
+        ctx.destroy_dsq(cgc.dsq);
+    }
+
+    fn cgroup_move(&mut self, ctx: &mut KernelCtx, task: TaskId, from: CgroupId, to: CgroupId) {
+        if matches!(
+            ctx.task(task).state,
+            TaskState::Runnable | TaskState::Running
+        ) {
+            self.update_runnable(ctx, from, false);
+            self.update_runnable(ctx, to, true);
+        }
+        // A task queued on the old cgroup's DSQ would be stranded there once
+        // that cgroup goes inactive, so it is requeued on the new one
+        if ctx.task_to_dsq.get(&task) == Some(&self.cgroups[&from].dsq) {
+            ctx.dsq_remove(task);
+            let prev_cpu = ctx.task(task).last_cpu.unwrap_or(0);
+            self.enqueue(ctx, task, SCX_ENQ_REENQ, prev_cpu);
+        }
+    }
+
+    fn cgroup_set_weight(&mut self, ctx: &mut KernelCtx, cgroup: CgroupId, weight: u64) {
+        let cgc = self.cgroup_ctx(cgroup);
+        let (old_weight, active) = (cgc.weight, cgc.is_active());
+        cgc.weight = weight;
+        if active && let Some(parent) = ctx.cgroup(cgroup).parent {
+            let pcgc = self.cgroup_ctx(parent);
+            pcgc.child_weight_sum = pcgc.child_weight_sum - old_weight + weight;
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::{collections::HashSet, error::Error, fmt, fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    core::{CpuId, KernelCtx, Task, Ticks},
+    scheduler::SCX_SLICE_DFL,
+};
+
+// A single predicate on a task. Rules within a group are ANDed.
+#[derive(Debug, Clone, Deserialize)]
+pub enum LayerMatch {
+    NamePrefix(String),
+    CgroupPrefix(String),
+    HasTag(String),
+    WeightAbove(u64),
+    WeightBelow(u64),
+    WeightEquals(u64),
+}
+
+impl LayerMatch {
+    pub fn matches(&self, ctx: &KernelCtx, task: &Task) -> bool {
+        match self {
+            Self::NamePrefix(prefix) => task.attrs.name.starts_with(prefix.as_str()),
+            Self::CgroupPrefix(prefix) => {
+                // Whole path components only, so "/app" matches "/app/web" but not "/apple"
+                let prefix = prefix.trim_end_matches('/');
+                let path = &ctx.cgroup(task.cgroup).path;
+                path.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            }
+            Self::HasTag(tag) => task.attrs.tags.contains(tag),
+            Self::WeightAbove(w) => task.weight > *w,
+            Self::WeightBelow(w) => task.weight < *w,
+            Self::WeightEquals(w) => task.weight == *w,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum LayerKind {
+    // Tasks only run on the layer's CPUs
+    Confined { cpus: Vec<CpuId> },
+    // The layer's CPUs are preferred, but tasks overflow onto unallocated CPUs
+    Grouped { cpus: Vec<CpuId> },
+    // Tasks run on unallocated CPUs, and on allocated CPUs that would otherwise idle
+    Open,
+}
+
+impl LayerKind {
+    pub fn cpus(&self) -> &[CpuId] {
+        match self {
+            Self::Confined { cpus } | Self::Grouped { cpus } => cpus,
+            Self::Open => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LayerSpec {
+    pub name: String,
+    // OR of AND-groups, as in scx_layered. An empty group matches every task.
+    pub matches: Vec<Vec<LayerMatch>>,
+    pub kind: LayerKind,
+    #[serde(default = "default_slice")]
+    pub slice: Ticks,
+    // Waking tasks may preempt tasks of non-preempting layers
+    #[serde(default)]
+    pub preempt: bool,
+}
+
+fn default_slice() -> Ticks {
+    SCX_SLICE_DFL
+}
+
+impl LayerSpec {
+    pub fn matches(&self, ctx: &KernelCtx, task: &Task) -> bool {
+        self.matches
+            .iter()
+            .any(|group| group.iter().all(|rule| rule.matches(ctx, task)))
+    }
+}
+
+// A JSON array of layers, in priority order. Tasks that match no layer are
+// placed in the last one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct LayeredConfig {
+    pub layers: Vec<LayerSpec>,
+}
+
+impl Default for LayeredConfig {
+    // A single open layer holding every task
+    fn default() -> Self {
+        Self {
+            layers: vec![LayerSpec {
+                name: "default".to_string(),
+                matches: vec![vec![]],
+                kind: LayerKind::Open,
+                slice: SCX_SLICE_DFL,
+                preempt: false,
+            }],
+        }
+    }
+}
+
+impl LayeredConfig {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LayeredConfigError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, LayeredConfigError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn validate(&self, num_cpus: usize) -> Result<(), LayeredConfigError> {
+        let invalid = |msg: String| Err(LayeredConfigError::Invalid(msg));
+
+        if self.layers.is_empty() {
+            return invalid("at least one layer is required".to_string());
+        }
+
+        let mut names = HashSet::new();
+        let mut owners: Vec<Option<&str>> = vec![None; num_cpus];
+        for layer in &self.layers {
+            let name = layer.name.as_str();
+            if !names.insert(name) {
+                return invalid(format!("duplicate layer name '{name}'"));
+            }
+            if layer.slice == 0 {
+                return invalid(format!("layer '{name}': slice must be nonzero"));
+            }
+            if layer.matches.is_empty() {
+                return invalid(format!(
+                    "layer '{name}': no match groups; use [[]] to match every task"
+                ));
+            }
+
+            let cpus = layer.kind.cpus();
+            if cpus.is_empty() && !matches!(layer.kind, LayerKind::Open) {
+                return invalid(format!("layer '{name}': no CPUs allocated"));
+            }
+            for &cpu in cpus {
+                let Some(owner) = owners.get_mut(cpu) else {
+                    return invalid(format!(
+                        "layer '{name}': CPU {cpu} out of range ({num_cpus} CPUs)"
+                    ));
+                };
+                if let Some(other) = owner {
+                    return invalid(format!(
+                        "layer '{name}': CPU {cpu} already allocated to layer '{other}'"
+                    ));
+                }
+                *owner = Some(name);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum LayeredConfigError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for LayeredConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read layered config: {err}"),
+            Self::Parse(err) => write!(f, "failed to parse layered config: {err}"),
+            Self::Invalid(msg) => write!(f, "invalid layered config: {msg}"),
+        }
+    }
+}
+
+impl Error for LayeredConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err),
+            Self::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for LayeredConfigError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for LayeredConfigError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Parse(err)
+    }
+}
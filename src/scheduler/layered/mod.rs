@@ -0,0 +1,218 @@
+pub mod config;
+
+use std::cmp;
+
+use rustc_hash::FxHashMap;
+
+use super::{
+    CpuId, EnqueueFlags, KernelCtx, SCX_KICK_PREEMPT, Scheduler, SelectCpuDecision, TaskId,
+    VTIME_PER_TICK, WakeFlags, scale_by_task_weight_inverse,
+};
+use crate::core::{DsqId, HigherClass, Vtime};
+pub use config::{LayerKind, LayerMatch, LayerSpec, LayeredConfig, LayeredConfigError};
+
+// Modeled on scx_layered: tasks are classified into layers once, at enable().
+// Each layer has a vtime-ordered DSQ and a CPU allocation.
+pub struct LayeredScheduler {
+    layers: Vec<Layer>,
+    // Layers each CPU dispatches from, owning layer first
+    dispatch_order: Vec<Vec<usize>>,
+    task_layer: FxHashMap<TaskId, usize>,
+    // Layers with every CPU offline, which any online CPU dispatches from last
+    stranded: Vec<usize>,
+}
+
+struct Layer {
+    spec: LayerSpec,
+    dsq: DsqId,
+    vtime_now: u64,
+    // CPUs the layer may run on, most preferred first
+    cpus: Vec<CpuId>,
+}
+
+impl LayeredScheduler {
+    pub fn new(ctx: &mut KernelCtx, config: LayeredConfig) -> Result<Self, LayeredConfigError> {
+        let num_cpus = ctx.cpus.len();
+        config.validate(num_cpus)?;
+
+        let mut owner = vec![None; num_cpus];
+        for (idx, spec) in config.layers.iter().enumerate() {
+            for &cpu in spec.kind.cpus() {
+                owner[cpu] = Some(idx);
+            }
+        }
+
+        let layers: Vec<Layer> = config
+            .layers
+            .into_iter()
+            .enumerate()
+            .map(|(idx, spec)| {
+                let own = (0..num_cpus).filter(|&cpu| owner[cpu] == Some(idx));
+                let unallocated = (0..num_cpus).filter(|&cpu| owner[cpu].is_none());
+                let others = (0..num_cpus).filter(|&cpu| owner[cpu].is_some_and(|o| o != idx));
+                let cpus = match spec.kind {
+                    LayerKind::Confined { .. } => own.collect(),
+                    LayerKind::Grouped { .. } => own.chain(unallocated).collect(),
+                    LayerKind::Open => unallocated.chain(others).collect(),
+                };
+                Layer {
+                    dsq: ctx.create_dsq_priq(),
+                    vtime_now: spec.slice * VTIME_PER_TICK,
+                    spec,
+                    cpus,
+                }
+            })
+            .collect();
+
+        let dispatch_order = (0..num_cpus)
+            .map(|cpu| {
+                let allowed = (0..layers.len()).filter(|&idx| layers[idx].cpus.contains(&cpu));
+                owner[cpu]
+                    .into_iter()
+                    .chain(allowed.filter(|&idx| owner[cpu] != Some(idx)))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            layers,
+            dispatch_order,
+            task_layer: FxHashMap::default(),
+            stranded: Vec::new(),
+        })
+    }
+
+    pub fn task_layer(&self, task: TaskId) -> Option<&str> {
+        self.task_layer
+            .get(&task)
+            .map(|&idx| self.layers[idx].spec.name.as_str())
+    }
+
+    fn classify(&self, ctx: &KernelCtx, task: TaskId) -> usize {
+        let task = ctx.task(task);
+        self.layers
+            .iter()
+            .position(|layer| layer.spec.matches(ctx, task))
+            .unwrap_or(self.layers.len() - 1)
+    }
+
+    fn layer_of(&self, task: TaskId) -> usize {
+        *self
+            .task_layer
+            .get(&task)
+            .expect("Task was not classified at enable()")
+    }
+
+    fn update_stranded(&mut self, ctx: &KernelCtx) {
+        self.stranded = (0..self.layers.len())
+            .filter(|&idx| {
+                self.layers[idx]
+                    .cpus
+                    .iter()
+                    .all(|&cpu| !ctx.cpu_is_online(cpu))
+            })
+            .collect();
+    }
+
+    // A CPU running a task from a non-preempting layer, with nothing else queued locally
+    fn pick_preempt_victim(&self, ctx: &KernelCtx, layer: usize) -> Option<CpuId> {
+        self.layers[layer].cpus.iter().copied().find(|&cpu| {
+            ctx.cpus[cpu].current.is_some_and(|current| {
+                !self.layers[self.layer_of(current)].spec.preempt
+                    && ctx.dsq_nr_queued(ctx.per_cpu_dsq(cpu)) == 0
+            })
+        })
+    }
+}
+
+impl Scheduler for LayeredScheduler {
+    const HOTPLUG_AWARE: bool = true;
+
+    fn init(ctx: &mut KernelCtx) -> Self {
+        Self::new(ctx, LayeredConfig::default()).expect("default layer config is valid")
+    }
+
+    fn select_cpu(
+        &mut self,
+        ctx: &mut KernelCtx,
+        task: TaskId,
+        _wakeup_cpu: CpuId,
+        _wake_flags: WakeFlags,
+    ) -> SelectCpuDecision {
+        let idx = self.layer_of(task);
+        let layer = &self.layers[idx];
+
+        if let Some(&cpu) = layer.cpus.iter().find(|&&cpu| ctx.cpu_is_free(cpu)) {
+            return SelectCpuDecision::DirectDispatch(cpu, layer.spec.slice);
+        }
+
+        if layer.spec.preempt
+            && let Some(cpu) = self.pick_preempt_victim(ctx, idx)
+        {
+            ctx.kick_cpu(cpu, SCX_KICK_PREEMPT);
+            return SelectCpuDecision::DirectDispatch(cpu, layer.spec.slice);
+        }
+
+        SelectCpuDecision::EnqueueOnDefault
+    }
+
+    fn enqueue(
+        &mut self,
+        ctx: &mut KernelCtx,
+        task: TaskId,
+        _flags: EnqueueFlags,
+        _prev_cpu: CpuId,
+    ) {
+        let layer = &self.layers[self.layer_of(task)];
+        let vtime = cmp::max(
+            ctx.task(task).vtime,
+            layer
+                .vtime_now
+                .saturating_sub(layer.spec.slice * VTIME_PER_TICK),
+        );
+        ctx.dsq_push_priq(layer.dsq, task, layer.spec.slice, Vtime(vtime));
+    }
+
+    fn dispatch(&mut self, ctx: &mut KernelCtx, cpu: CpuId) {
+        for &idx in self.dispatch_order[cpu].iter().chain(&self.stranded) {
+            if ctx.dsq_move_to_local(self.layers[idx].dsq, cpu) {
+                return;
+            }
+        }
+    }
+
+    fn running(&mut self, ctx: &mut KernelCtx, task: TaskId) {
+        let idx = self.layer_of(task);
+        let layer = &mut self.layers[idx];
+        layer.vtime_now = cmp::max(layer.vtime_now, ctx.task(task).vtime);
+    }
+
+    fn stopping(&mut self, ctx: &mut KernelCtx, task: TaskId, _runnable: bool) {
+        let task = ctx.task_mut(task);
+        task.vtime += scale_by_task_weight_inverse(task, task.consumed_timeslice);
+    }
+
+    // Tasks dispatched straight to this CPU, onto a free or preempted layer
+    // CPU, go back to their layer's DSQ for its other CPUs to run
+    fn cpu_release(&mut self, ctx: &mut KernelCtx, cpu: CpuId, _class: HigherClass) {
+        ctx.reenqueue_local(cpu);
+    }
+
+    fn cpu_online(&mut self, ctx: &mut KernelCtx, _cpu: CpuId) {
+        self.update_stranded(ctx);
+    }
+
+    fn cpu_offline(&mut self, ctx: &mut KernelCtx, _cpu: CpuId) {
+        self.update_stranded(ctx);
+    }
+
+    fn enable(&mut self, ctx: &mut KernelCtx, task: TaskId) {
+        let idx = self.classify(ctx, task);
+        self.task_layer.insert(task, idx);
+        ctx.task_mut(task).vtime = self.layers[idx].vtime_now;
+    }
+
+    fn disable(&mut self, _ctx: &mut KernelCtx, task: TaskId) {
+        self.task_layer.remove(&task);
+    }
+}
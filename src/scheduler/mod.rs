@@ -1,11 +1,17 @@
+pub mod edf;
 pub mod fifo;
+pub mod flatcg;
+pub mod layered;
 pub mod priq;
 
 use crate::core::{
-    Ticks,
-    state::{CpuId, KernelCtx, TaskId},
+    CgroupId, HigherClass, NICE_0_WEIGHT, Ticks,
+    state::{CpuId, KernelCtx, Task, TaskId},
 };
+pub use edf::EdfScheduler;
 pub use fifo::FifoScheduler;
+pub use flatcg::FlatcgScheduler;
+pub use layered::LayeredScheduler;
 pub use priq::PriqScheduler;
 
 pub type EnqueueFlags = u64;
@@ -19,8 +25,29 @@ pub const SCX_ENQ_LAST: EnqueueFlags = 1 << 41;
 pub const SCX_ENQ_CLEAR_OPSS: EnqueueFlags = 1 << 56;
 pub const SCX_ENQ_DSQ_PRIQ: EnqueueFlags = 1 << 57;
 
+pub type WakeFlags = u64;
+
+pub const SCX_WAKE_FORK: WakeFlags = 0x04;
+pub const SCX_WAKE_TTWU: WakeFlags = 0x08;
+// The waker is about to sleep, so its CPU will free up for the wakee
+pub const SCX_WAKE_SYNC: WakeFlags = 0x10;
+
+pub type KickFlags = u64;
+
+pub const SCX_KICK_IDLE: KickFlags = 1 << 0;
+pub const SCX_KICK_PREEMPT: KickFlags = 1 << 1;
+
 pub const SCX_SLICE_DFL: u64 = 3;
 
+// Vtime advances by this much per tick of service at NICE_0_WEIGHT. Ticks are
+// coarse, so this keeps high-weight charges from truncating to zero.
+pub const VTIME_PER_TICK: u64 = 1024;
+
+// Vtime charge for `ticks` of service, inversely proportional to task weight
+pub fn scale_by_task_weight_inverse(task: &Task, ticks: Ticks) -> u64 {
+    ticks * VTIME_PER_TICK * NICE_0_WEIGHT / task.weight
+}
+
 #[derive(Debug)]
 pub enum SelectCpuDecision {
     DirectDispatch(CpuId, Ticks),
@@ -29,6 +56,10 @@ pub enum SelectCpuDecision {
 }
 
 pub trait Scheduler {
+    // Hotplug-aware schedulers have tasks they dispatch to offline CPUs
+    // redirected to the global DSQ; any other scheduler is ejected instead
+    const HOTPLUG_AWARE: bool = false;
+
     fn init(ctx: &mut KernelCtx) -> Self;
 
     fn exit(&mut self, _ctx: &mut KernelCtx) {}
@@ -38,17 +69,51 @@ pub trait Scheduler {
         ctx: &mut KernelCtx,
         task: TaskId,
         wakeup_cpu: CpuId,
+        wake_flags: WakeFlags,
     ) -> SelectCpuDecision;
 
     fn enqueue(&mut self, ctx: &mut KernelCtx, task: TaskId, flags: EnqueueFlags, prev_cpu: CpuId);
 
     fn dispatch(&mut self, ctx: &mut KernelCtx, cpu: CpuId);
 
+    // Task became runnable (woken); paired with quiescent()
+    fn runnable(&mut self, _ctx: &mut KernelCtx, _task: TaskId, _flags: EnqueueFlags) {}
+
+    // Task stopped being runnable (completed)
+    fn quiescent(&mut self, _ctx: &mut KernelCtx, _task: TaskId) {}
+
     fn running(&mut self, _ctx: &mut KernelCtx, _task: TaskId) {}
 
     fn stopping(&mut self, _ctx: &mut KernelCtx, _task: TaskId, _runnable: bool) {}
 
     fn enable(&mut self, _ctx: &mut KernelCtx, _task: TaskId) {}
 
+    // The task exited and is about to be freed; drop any state kept for it
+    fn disable(&mut self, _ctx: &mut KernelCtx, _task: TaskId) {}
+
     fn tick(&mut self, _ctx: &mut KernelCtx, _task: TaskId) {}
+
+    // Task weight changed (renice); ctx already holds the new weight
+    fn set_weight(&mut self, _ctx: &mut KernelCtx, _task: TaskId, _weight: u64) {}
+
+    // `cpu` was taken by a higher sched class; tasks left on its local DSQ are
+    // stranded until cpu_acquire() unless re-enqueued with ctx.reenqueue_local()
+    fn cpu_release(&mut self, _ctx: &mut KernelCtx, _cpu: CpuId, _class: HigherClass) {}
+
+    fn cpu_acquire(&mut self, _ctx: &mut KernelCtx, _cpu: CpuId) {}
+
+    fn cpu_online(&mut self, _ctx: &mut KernelCtx, _cpu: CpuId) {}
+
+    // Runs after the CPU's current task is taken off it, before the tasks left
+    // on it are re-enqueued
+    fn cpu_offline(&mut self, _ctx: &mut KernelCtx, _cpu: CpuId) {}
+
+    fn cgroup_init(&mut self, _ctx: &mut KernelCtx, _cgroup: CgroupId) {}
+
+    fn cgroup_exit(&mut self, _ctx: &mut KernelCtx, _cgroup: CgroupId) {}
+
+    fn cgroup_move(&mut self, _ctx: &mut KernelCtx, _task: TaskId, _from: CgroupId, _to: CgroupId) {
+    }
+
+    fn cgroup_set_weight(&mut self, _ctx: &mut KernelCtx, _cgroup: CgroupId, _weight: u64) {}
 }
@@ -1,9 +1,9 @@
 use std::cmp;
 
-use super::{CpuId, EnqueueFlags, KernelCtx, Scheduler, SelectCpuDecision, TaskId};
+use super::{CpuId, EnqueueFlags, KernelCtx, Scheduler, SelectCpuDecision, TaskId, WakeFlags};
 use crate::{
-    core::{DsqId, Vtime},
-    scheduler::SCX_SLICE_DFL,
+    core::{DsqId, HigherClass, Vtime},
+    scheduler::{SCX_SLICE_DFL, SCX_WAKE_SYNC, VTIME_PER_TICK, scale_by_task_weight_inverse},
 };
 
 pub struct PriqScheduler {
@@ -15,7 +15,7 @@ impl Scheduler for PriqScheduler {
     fn init(ctx: &mut KernelCtx) -> Self {
         Self {
             global_priq: ctx.create_dsq_priq(),
-            vtime_now: SCX_SLICE_DFL,
+            vtime_now: SCX_SLICE_DFL * VTIME_PER_TICK,
         }
     }
 
@@ -23,8 +23,16 @@ impl Scheduler for PriqScheduler {
         &mut self,
         ctx: &mut KernelCtx,
         _task: TaskId,
-        _wakeup_cpu: CpuId,
+        wakeup_cpu: CpuId,
+        wake_flags: WakeFlags,
     ) -> SelectCpuDecision {
+        // Wake-affine: the waker's CPU is about to be free, and its cache is warm
+        if wake_flags & SCX_WAKE_SYNC != 0
+            && ctx.cpu_is_online(wakeup_cpu)
+            && ctx.dsq_nr_queued(ctx.per_cpu_dsq(wakeup_cpu)) == 0
+        {
+            return SelectCpuDecision::DirectDispatch(wakeup_cpu, SCX_SLICE_DFL);
+        }
         if let Some(cpu) = ctx.pick_idle_cpu() {
             SelectCpuDecision::DirectDispatch(cpu, SCX_SLICE_DFL)
         } else {
@@ -41,7 +49,8 @@ impl Scheduler for PriqScheduler {
     ) {
         let vtime = cmp::max(
             ctx.task(task).vtime,
-            self.vtime_now.saturating_sub(SCX_SLICE_DFL),
+            self.vtime_now
+                .saturating_sub(SCX_SLICE_DFL * VTIME_PER_TICK),
         );
         ctx.dsq_push_priq(self.global_priq, task, SCX_SLICE_DFL, Vtime(vtime));
     }
@@ -57,7 +66,13 @@ impl Scheduler for PriqScheduler {
 
     fn stopping(&mut self, ctx: &mut KernelCtx, task: TaskId, _runnable: bool) {
         let task = ctx.task_mut(task);
-        task.vtime += (task.consumed_timeslice * 100) / task.weight;
+        task.vtime += scale_by_task_weight_inverse(task, task.consumed_timeslice);
+    }
+
+    // Idle CPUs are filled by direct dispatch, bypassing the global priq; send
+    // those tasks back through it so another CPU can run them in vtime order
+    fn cpu_release(&mut self, ctx: &mut KernelCtx, cpu: CpuId, _class: HigherClass) {
+        ctx.reenqueue_local(cpu);
     }
 
     fn enable(&mut self, ctx: &mut KernelCtx, task: TaskId) {
@@ -0,0 +1,69 @@
+use rand::prelude::*;
+use rand_distr::Exp;
+
+use super::job::Job;
+use crate::core::Ticks;
+
+// Index into Sim's clients
+pub type ClientId = usize;
+
+// Time a client waits between a completion and its next submission
+#[derive(Debug, Clone, Copy)]
+pub enum ThinkTime {
+    Fixed(Ticks),
+    // Inclusive of both ends
+    Uniform { min: Ticks, max: Ticks },
+    Exponential { mean: f64 },
+}
+
+// Closed-loop client: submits `job` at its arrival_time, waits for it to
+// complete, thinks, and submits it again, `requests` times in total
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub job: Job,
+    pub think_time: ThinkTime,
+    pub requests: u64,
+    pub seed: u64,
+}
+
+pub(crate) struct ClientState {
+    pub client: Client,
+    pub submitted: u64,
+    rng: StdRng,
+}
+
+impl ClientState {
+    pub fn new(client: Client) -> Self {
+        match client.think_time {
+            ThinkTime::Uniform { min, max } => {
+                assert!(min <= max, "Think time range {min}..={max} is empty")
+            }
+            ThinkTime::Exponential { mean } => assert!(
+                mean.is_finite() && mean > 0.0,
+                "Think time mean {mean} must be positive"
+            ),
+            ThinkTime::Fixed(_) => {}
+        }
+        let rng = StdRng::seed_from_u64(client.seed);
+        Self {
+            client,
+            submitted: 0,
+            rng,
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.submitted >= self.client.requests
+    }
+
+    pub fn think(&mut self) -> Ticks {
+        match self.client.think_time {
+            ThinkTime::Fixed(ticks) => ticks,
+            ThinkTime::Uniform { min, max } => self.rng.random_range(min..=max),
+            ThinkTime::Exponential { mean } => {
+                let exp = Exp::new(1.0 / mean).expect("invalid think time mean");
+                exp.sample(&mut self.rng).round() as Ticks
+            }
+        }
+    }
+}
@@ -1,90 +1,291 @@
+use std::{cmp, collections::BTreeMap};
+
 use num_traits::AsPrimitive;
 use rustc_hash::FxHashMap;
 
-use super::job::{Job, JobInstance};
+use super::{
+    client::{Client, ClientId, ClientState},
+    energy::{EnergyMeter, EnergyModel},
+    event::SimEvent,
+    job::{ForkJoin, Job, JobId, JobInstance},
+    source::{JobSink, JobSource},
+};
 use crate::{
     SchedCoreEvent,
-    core::{TaskId, TaskState, driver::SchedCore, state::CpuId},
-    scheduler::Scheduler,
+    core::{BlockReason, KernelCtx, TaskId, TaskState, Ticks, driver::SchedCore, nice_to_weight},
+    scheduler::{SCX_WAKE_FORK, SCX_WAKE_SYNC, SCX_WAKE_TTWU, Scheduler},
 };
 
+// Children of a forked parent task that have not yet exited
+struct ForkGroup {
+    spec: ForkJoin,
+    forked: bool,
+    live: u64,
+    // Barriers passed so far, and the children waiting at the next one
+    barriers: u64,
+    at_barrier: Vec<TaskId>,
+}
+
+// A job waiting to be woken by each release of its waker, from `first` on
+struct Wakee {
+    job: Job,
+    first: u64,
+    // Releases not yet woken
+    left: u64,
+}
+
 pub struct Sim<S: Scheduler> {
     pub core: SchedCore<S>,
+    // Completed jobs, in order of completion, unless a sink is set
     pub jobs: Vec<JobInstance>,
-    job_cursor: usize,
     num_cpus: usize,
 
-    // TaskId --> job[index] map; used to propagate task completion to Job object
-    tasks_to_jobs: FxHashMap<TaskId, usize>,
-    // For O(1) completion check
+    // None once exhausted, and the next job pulled from it
+    source: Option<Box<dyn JobSource>>,
+    next_job: Option<Job>,
+    last_arrival: Ticks,
+    sink: Option<Box<dyn JobSink>>,
+    // Released jobs, keyed on their task
+    live: FxHashMap<TaskId, JobInstance>,
+    // Releases waiting for their arrival time
+    pending: BTreeMap<Ticks, Vec<JobInstance>>,
     num_jobs_complete: usize,
+    num_deadline_misses: usize,
+    num_lock_holder_preemptions: usize,
+    scripted_events: BTreeMap<Ticks, Vec<SimEvent>>,
+    // Waker job --> jobs released as its releases complete
+    wakees: FxHashMap<JobId, Vec<Wakee>>,
+    // Applied to jobs released after the renice, until their last release completes
+    renices: FxHashMap<JobId, i32>,
+    energy: Option<EnergyMeter>,
+    // Parent task --> its children, and child task --> parent task
+    forks: FxHashMap<TaskId, ForkGroup>,
+    fork_parents: FxHashMap<TaskId, TaskId>,
+    clients: Vec<ClientState>,
+    // Next submission time --> clients submitting then
+    submissions: BTreeMap<Ticks, Vec<ClientId>>,
+    // Completed tasks to free at the next step, once a sink is set
+    reap: Vec<TaskId>,
 }
 
 impl<S: Scheduler> Sim<S> {
-    pub fn new(mut jobs: Vec<Job>, num_cpus: usize) -> Self {
-        debug_assert!(num_cpus > 0, "Simulation requires at least one CPU");
+    pub fn new(jobs: Vec<Job>, num_cpus: usize) -> Self {
+        Self::with_scheduler(jobs, num_cpus, S::init)
+    }
 
-        jobs.sort_by(|a, b| {
-            a.arrival_time
-                .cmp(&b.arrival_time)
-                .then_with(|| a.id.cmp(&b.id))
-        });
-        let jobs = jobs
-            .into_iter()
-            .map(|job| {
-                debug_assert!(job.run_time > 0, "Job runtime must be nonzero");
-                JobInstance {
-                    job,
-                    start_time: None,
-                    completion_time: None,
-                }
-            })
-            .collect();
+    pub fn with_scheduler(
+        jobs: Vec<Job>,
+        num_cpus: usize,
+        init: impl FnOnce(&mut KernelCtx) -> S,
+    ) -> Self {
+        Self::with_core(jobs, SchedCore::with_scheduler(num_cpus, init))
+    }
+
+    pub fn with_core(jobs: Vec<Job>, core: SchedCore<S>) -> Self {
+        let num_jobs = jobs.len();
+        let job_releases: FxHashMap<JobId, u64> =
+            jobs.iter().map(|job| (job.id, job.releases())).collect();
+        let mut roots = Vec::new();
+        let mut woken: FxHashMap<JobId, Vec<Job>> = FxHashMap::default();
+        for job in jobs {
+            let Some(waker) = job.woken_by else {
+                roots.push(job);
+                continue;
+            };
+            assert!(
+                job_releases
+                    .get(&waker)
+                    .is_some_and(|&releases| releases >= job.releases()),
+                "Job {} is woken by missing job {waker}",
+                job.id
+            );
+            woken.entry(waker).or_default().push(job);
+        }
+
+        // Each job is followed by the jobs it wakes, so that they are waiting
+        // from when it arrives
+        roots.sort_by_key(|job| (job.arrival_time, job.id));
+        let mut order = Vec::with_capacity(num_jobs);
+        let mut stack: Vec<Job> = roots.into_iter().rev().collect();
+        while let Some(job) = stack.pop() {
+            if let Some(mut wakees) = woken.remove(&job.id) {
+                wakees.sort_by_key(|wakee| cmp::Reverse(wakee.id));
+                stack.extend(wakees);
+            }
+            order.push(job);
+        }
+        assert!(
+            order.len() == num_jobs,
+            "Jobs woken by each other in a cycle"
+        );
+        Self::with_source(order.into_iter(), core)
+    }
 
-        Self {
-            core: SchedCore::<S>::new(num_cpus),
-            jobs,
-            job_cursor: 0,
+    pub fn with_source(source: impl JobSource + 'static, core: SchedCore<S>) -> Self {
+        let num_cpus = core.ctx.cpus.len();
+        debug_assert!(num_cpus > 0, "Simulation requires at least one CPU");
+
+        let mut sim = Self {
+            core,
+            jobs: Vec::new(),
             num_cpus,
-            tasks_to_jobs: FxHashMap::default(),
+            source: Some(Box::new(source)),
+            next_job: None,
+            last_arrival: 0,
+            sink: None,
+            live: FxHashMap::default(),
+            pending: BTreeMap::new(),
+            wakees: FxHashMap::default(),
+            renices: FxHashMap::default(),
             num_jobs_complete: 0,
+            num_deadline_misses: 0,
+            num_lock_holder_preemptions: 0,
+            scripted_events: BTreeMap::new(),
+            energy: None,
+            forks: FxHashMap::default(),
+            fork_parents: FxHashMap::default(),
+            clients: Vec::new(),
+            submissions: BTreeMap::new(),
+            reap: Vec::new(),
+        };
+        sim.peek_job();
+        sim
+    }
+
+    // Completed jobs go to `sink` rather than `jobs`, and their tasks are
+    // freed, so that memory stays bounded however long the simulation runs
+    pub fn set_sink(&mut self, sink: impl JobSink + 'static) {
+        self.sink = Some(Box::new(sink));
+    }
+
+    // Client jobs are released as they are submitted
+    pub fn add_client(&mut self, client: Client) -> ClientId {
+        debug_assert!(client.job.run_time > 0, "Job runtime must be nonzero");
+        debug_assert!(
+            client.job.period.is_none() && client.job.woken_by.is_none(),
+            "Client jobs cannot be periodic or woken by other jobs"
+        );
+        let id = self.clients.len();
+        let first = cmp::max(client.job.arrival_time, self.core.now());
+        let client = ClientState::new(client);
+        if !client.done() {
+            self.submissions.entry(first).or_default().push(id);
+        }
+        self.clients.push(client);
+        id
+    }
+
+    // Events scheduled in the past are applied at the next step
+    pub fn schedule(&mut self, at: Ticks, event: SimEvent) {
+        self.scripted_events.entry(at).or_default().push(event);
+    }
+
+    pub fn schedule_all(&mut self, events: impl IntoIterator<Item = (Ticks, SimEvent)>) {
+        for (at, event) in events {
+            self.schedule(at, event);
         }
     }
 
     pub fn step(&mut self) -> Vec<SchedCoreEvent> {
+        self.step_timed()
+            .into_iter()
+            .map(|(_, event)| event)
+            .collect()
+    }
+
+    // As step(), with each event's time; see SchedCore::tick_timed
+    pub fn step_timed(&mut self) -> Vec<(Ticks, SchedCoreEvent)> {
+        for task in std::mem::take(&mut self.reap) {
+            self.core.reap_task(task);
+        }
+        self.handle_scripted_events();
         self.handle_arrivals();
 
         let now = self.core.now();
-        let events = self.core.tick();
+        let events = self.core.tick_timed();
+        self.account_energy();
 
-        for event in events.iter() {
+        for (_, event) in events.iter() {
             match event {
                 SchedCoreEvent::TaskStateChange {
                     task,
                     to: TaskState::Completed,
                     ..
                 } => {
-                    let job_index = self
-                        .tasks_to_jobs
-                        .remove(&task)
+                    if self.sink.is_some() {
+                        self.reap.push(*task);
+                    }
+                    if let Some(parent) = self.fork_parents.remove(task) {
+                        self.child_exit(parent, *task);
+                        continue;
+                    }
+                    self.forks.remove(task);
+
+                    let mut job = self
+                        .live
+                        .remove(task)
                         .expect("Completed job missing associated task");
 
                     // The job was serviced during timestep "now"
                     // We report the first full timestep during which job is complete.
-                    self.jobs[job_index].completion_time = Some(now + 1);
+                    job.completion_time = Some(now + 1);
+                    job.migrations = self.core.ctx.task(*task).nr_migrations;
+                    job.lock_wait = self.core.ctx.task(*task).lock_wait;
+                    job.lateness = job
+                        .absolute_deadline()
+                        .map(|deadline| (now + 1) as i64 - deadline as i64);
+                    if job.missed_deadline() {
+                        self.num_deadline_misses += 1;
+                    }
                     self.num_jobs_complete += 1;
+
+                    // Closed-loop clients think before submitting again
+                    if let Some(client_id) = job.client {
+                        let client = &mut self.clients[client_id];
+                        if !client.done() {
+                            let at = now + 1 + client.think();
+                            self.submissions.entry(at).or_default().push(client_id);
+                        }
+                    }
+
+                    self.wake_wakees(job.job.id, job.release, *task);
+                    if self.renices.contains_key(&job.job.id)
+                        && self.next_release(job.job.id).is_none()
+                    {
+                        self.renices.remove(&job.job.id);
+                    }
+
+                    match &mut self.sink {
+                        Some(sink) => sink.complete(job),
+                        None => self.jobs.push(job),
+                    }
                 }
                 SchedCoreEvent::TaskStateChange {
                     task,
                     to: TaskState::Running,
                     ..
                 } => {
-                    let job_index = *self
-                        .tasks_to_jobs
-                        .get(&task)
-                        .expect("Running job missing associated task");
-
-                    self.jobs[job_index].start_time.get_or_insert(now);
+                    let root = self.fork_parents.get(task).unwrap_or(task);
+                    self.live
+                        .get_mut(root)
+                        .expect("Running job missing associated task")
+                        .start_time
+                        .get_or_insert(now);
+                }
+                // Lock sleeps are the core's business; only block points mean anything here
+                SchedCoreEvent::TaskStateChange {
+                    task,
+                    reason: Some(BlockReason::BlockAt),
+                    ..
+                } => {
+                    if let Some(&parent) = self.fork_parents.get(task) {
+                        self.child_at_barrier(parent, *task);
+                    } else if self.forks.contains_key(task) {
+                        self.fork(*task);
+                    }
+                }
+                SchedCoreEvent::LockHolderPreempted { .. } => {
+                    self.num_lock_holder_preemptions += 1;
                 }
                 _ => {}
             }
@@ -93,25 +294,448 @@ impl<S: Scheduler> Sim<S> {
         events
     }
 
-    fn handle_arrivals(&mut self) {
+    // Energy is integrated from the step after the model is set
+    pub fn set_energy_model(&mut self, model: impl EnergyModel + 'static) {
+        self.energy = Some(EnergyMeter::new(Box::new(model), self.num_cpus));
+    }
+
+    pub fn total_energy(&self) -> Option<f64> {
+        self.energy.as_ref().map(|meter| meter.total)
+    }
+
+    // Energy of idle CPUs and wakeups, not attributed to any job
+    pub fn idle_energy(&self) -> Option<f64> {
+        self.energy.as_ref().map(|meter| meter.idle)
+    }
+
+    // Energy of busy CPUs on which no job made progress, such as those taken
+    // by a higher class. With idle_energy() and each job's energy, this makes
+    // up total_energy().
+    pub fn stolen_energy(&self) -> Option<f64> {
+        self.energy.as_ref().map(|meter| meter.stolen)
+    }
+
+    fn account_energy(&mut self) {
+        let Some(meter) = &mut self.energy else {
+            return;
+        };
+        let activity = self.core.last_activity();
+        let task_energy = meter.record(activity);
+        for (activity, energy) in activity.iter().zip(task_energy) {
+            if let Some(task) = activity.ran {
+                let root = self.fork_parents.get(&task).unwrap_or(&task);
+                self.live
+                    .get_mut(root)
+                    .expect("Running job missing associated task")
+                    .energy += energy;
+            }
+        }
+    }
+
+    fn handle_scripted_events(&mut self) {
         let now = self.core.now();
-        let arriving_jobs = self.jobs[self.job_cursor..]
+        while let Some(entry) = self.scripted_events.first_entry()
+            && *entry.key() <= now
+        {
+            for event in entry.remove() {
+                match event {
+                    SimEvent::Renice { job, nice } => self.renice(job, nice),
+                    SimEvent::Interference { cpu, .. } if !self.core.ctx.cpu_is_online(cpu) => {}
+                    SimEvent::Interference {
+                        cpu,
+                        class,
+                        duration,
+                    } => self.core.steal_cpu(cpu, class, duration),
+                    SimEvent::CpuOffline { cpu } => self.core.cpu_offline(cpu),
+                    SimEvent::CpuOnline { cpu } => self.core.cpu_online(cpu),
+                }
+            }
+        }
+    }
+
+    fn renice(&mut self, job_id: JobId, nice: i32) {
+        let weight = nice_to_weight(nice);
+        let live_tasks: Vec<TaskId> = self
+            .live
             .iter_mut()
-            .take_while(|job| job.job.arrival_time == now); // This will be contiguous, since jobs are sorted
+            .filter(|(_, job)| job.job.id == job_id)
+            .map(|(&task, job)| {
+                job.job.nice = Some(nice);
+                task
+            })
+            .collect();
+        let children: Vec<TaskId> = self
+            .fork_parents
+            .iter()
+            .filter(|(_, parent)| live_tasks.contains(parent))
+            .map(|(&child, _)| child)
+            .collect();
+        for task in live_tasks.into_iter().chain(children) {
+            self.core.set_task_weight(task, weight);
+        }
+        self.renices.insert(job_id, nice);
+    }
 
-        for job in arriving_jobs {
-            let task_id = self.core.create_task(job.job.run_time, job.job.weight);
-            self.tasks_to_jobs.insert(task_id, self.job_cursor);
+    // Fills `next_job` from the source, if it is not yet exhausted
+    fn peek_job(&mut self) -> Option<&Job> {
+        if self.next_job.is_none()
+            && let Some(source) = &mut self.source
+        {
+            self.next_job = source.next_job();
+            if self.next_job.is_none() {
+                self.source = None;
+            }
+        }
+        self.next_job.as_ref()
+    }
+
+    // The earliest release of `job_id` still to complete, if any are left
+    fn next_release(&self, job_id: JobId) -> Option<u64> {
+        let live = self
+            .live
+            .values()
+            .chain(self.pending.values().flatten())
+            .filter(|job| job.job.id == job_id)
+            .map(|job| job.release);
+        let clients = self
+            .clients
+            .iter()
+            .filter(|client| client.client.job.id == job_id && !client.done())
+            .map(|client| client.submitted);
+        let wakees = self
+            .wakees
+            .values()
+            .flatten()
+            .filter(|wakee| wakee.job.id == job_id)
+            .map(|wakee| wakee.job.releases() - wakee.left);
+        live.chain(clients).chain(wakees).min()
+    }
+
+    // Releases `job` from `release` on, as if it were not woken by another job
+    fn release_unwoken(&mut self, job: Job, release: u64) {
+        let job = Job {
+            arrival_time: self.core.now(),
+            ..job
+        };
+        self.release_job(JobInstance::new(job, release), None);
+    }
+
+    fn handle_arrivals(&mut self) {
+        let now = self.core.now();
+        while let Some(job) = self.peek_job()
+            && (job.woken_by.is_some() || job.arrival_time <= now)
+        {
+            let job = self.next_job.take().unwrap();
+            self.admit(job);
+        }
+
+        while let Some(entry) = self.pending.first_entry()
+            && *entry.key() <= now
+        {
+            let mut due = entry.remove();
+            due.sort_by_key(|job| (job.job.id, job.release));
+            for job in due {
+                self.release_job(job, None);
+            }
+        }
+
+        while let Some(entry) = self.submissions.first_entry()
+            && *entry.key() <= now
+        {
+            for client in entry.remove() {
+                self.submit(client);
+            }
+        }
+    }
+
+    // Queue a job from the source: on its waker, or until its first release
+    fn admit(&mut self, job: Job) {
+        debug_assert!(job.run_time > 0, "Job runtime must be nonzero");
+        debug_assert!(
+            job.fork.is_none_or(|fork| fork.at < job.run_time
+                && fork.children > 0
+                && fork.child_run_time > 0
+                && fork.barrier_interval != Some(0)),
+            "Fork must happen before the job's runtime, with nonzero children, child runtime and barrier interval"
+        );
+        debug_assert!(
+            job.critical_sections.iter().all(|section| section.len > 0)
+                && job
+                    .critical_sections
+                    .windows(2)
+                    .all(|pair| pair[0].start + pair[0].len <= pair[1].start)
+                && job
+                    .critical_sections
+                    .last()
+                    .is_none_or(|section| section.start + section.len <= job.run_time),
+            "Critical sections must be nonempty, ordered, non-overlapping and within the job's runtime"
+        );
+        debug_assert!(
+            job.period.is_none_or(|period| period.interval > 0),
+            "Job period must be nonzero"
+        );
+
+        if let Some(waker) = job.woken_by {
+            let left = job.releases();
+            if left == 0 {
+                return;
+            }
+            match self.next_release(waker) {
+                Some(first) => {
+                    self.wakees
+                        .entry(waker)
+                        .or_default()
+                        .push(Wakee { job, first, left })
+                }
+                // Its waker has completed, or never came up first
+                None => self.release_unwoken(job, 0),
+            }
+            return;
+        }
+
+        debug_assert!(
+            job.arrival_time >= self.last_arrival,
+            "Job {} arrives out of order",
+            job.id
+        );
+        self.last_arrival = job.arrival_time;
+        if job.releases() > 0 {
+            let arrival = cmp::max(job.arrival_time, self.core.now());
+            self.pending
+                .entry(arrival)
+                .or_default()
+                .push(JobInstance::new(job, 0));
+        }
+    }
+
+    // Releases the jobs woken by `release` of `waker_job`, which ran as
+    // `task`. Jobs its remaining releases can no longer wake are released on
+    // their own.
+    fn wake_wakees(&mut self, waker_job: JobId, release: u64, task: TaskId) {
+        let Some(wakees) = self.wakees.get_mut(&waker_job) else {
+            return;
+        };
+        let mut woken = Vec::new();
+        for wakee in wakees.iter_mut() {
+            if let Some(index) = release.checked_sub(wakee.first)
+                && index < wakee.job.releases()
+            {
+                wakee.left -= 1;
+                let job = Job {
+                    arrival_time: self.core.now(),
+                    ..wakee.job.clone()
+                };
+                woken.push(JobInstance::new(job, index));
+            }
+        }
+        wakees.retain(|wakee| wakee.left > 0);
+        if wakees.is_empty() {
+            self.wakees.remove(&waker_job);
+        }
+        for job in woken {
+            self.release_job(job, Some(task));
+        }
+
+        if self.wakees.contains_key(&waker_job) && self.next_release(waker_job).is_none() {
+            for wakee in self.wakees.remove(&waker_job).unwrap_or_default() {
+                let release = wakee.job.releases() - wakee.left;
+                self.release_unwoken(wakee.job, release);
+            }
+        }
+    }
+
+    fn submit(&mut self, client_id: ClientId) {
+        let now = self.core.now();
+        let client = &mut self.clients[client_id];
+        let mut job = JobInstance::new(
+            Job {
+                arrival_time: now,
+                ..client.client.job.clone()
+            },
+            client.submitted,
+        );
+        job.client = Some(client_id);
+        client.submitted += 1;
+        self.release_job(job, None);
+    }
+
+    fn release_job(&mut self, mut job: JobInstance, waker: Option<TaskId>) {
+        if let Some(&nice) = self.renices.get(&job.job.id) {
+            job.job.nice = Some(nice);
+        }
+
+        // Recurring jobs queue their next release as each one arrives
+        if waker.is_none()
+            && let Some(period) = job.job.period
+            && job.release + 1 < period.releases
+        {
+            let next = JobInstance::new(
+                Job {
+                    arrival_time: job.job.arrival_time + period.interval,
+                    ..job.job.clone()
+                },
+                job.release + 1,
+            );
+            self.pending
+                .entry(next.job.arrival_time)
+                .or_default()
+                .push(next);
+        }
+
+        let cgroup = self.core.ensure_cgroup(&job.job.cgroup);
+        let task_id = self.core.create_task(
+            job.job.run_time,
+            job.job.task_weight(),
+            job.absolute_deadline(),
+            cgroup,
+            job.job.attrs(),
+        );
+
+        for section in &job.job.critical_sections {
+            assert!(
+                section.lock < self.core.ctx.locks.len(),
+                "Job {} uses missing lock {}",
+                job.job.id,
+                section.lock
+            );
+        }
+        self.core.ctx.task_mut(task_id).critical_sections =
+            job.job.critical_sections.iter().copied().collect();
+
+        if let Some(spec) = job.job.fork {
+            self.core.ctx.task_mut(task_id).block_at = Some(spec.at);
+            self.forks.insert(
+                task_id,
+                ForkGroup {
+                    spec,
+                    forked: false,
+                    live: 0,
+                    barriers: 0,
+                    at_barrier: Vec::new(),
+                },
+            );
+        }
+
+        let (job_id, sync) = (job.job.id, job.job.sync_wakeup);
+        let waker = waker.or_else(|| {
+            let waker = job.job.waker?;
+            self.live.iter().find_map(|(&task, live)| {
+                (live.job.id == waker && self.core.ctx.task(task).last_cpu.is_some())
+                    .then_some(task)
+            })
+        });
+        self.live.insert(task_id, job);
+
+        if let Some(waker) = waker {
+            let flags = if sync {
+                SCX_WAKE_TTWU | SCX_WAKE_SYNC
+            } else {
+                SCX_WAKE_TTWU
+            };
+            self.core.wake_task_by(task_id, waker, flags);
+            return;
+        }
+        // Spread wakeups across CPUs, skipping offline ones
+        let wakeup_cpu = (0..self.num_cpus)
+            .map(|offset| (job_id as usize + offset) % self.num_cpus)
+            .find(|&cpu| self.core.ctx.cpu_is_online(cpu))
+            .expect("At least one CPU must be online");
+        self.core.wake_task(task_id, wakeup_cpu);
+    }
+
+    // The parent reached its fork point and went to sleep
+    fn fork(&mut self, parent: TaskId) {
+        let job = &self.live[&parent];
+        let group = self
+            .forks
+            .get_mut(&parent)
+            .expect("Forking task has no group");
+        debug_assert!(!group.forked, "Task {parent} forked twice");
+        group.forked = true;
+        group.live = group.spec.children;
 
-            let wakeup_cpu = (job.job.id % self.num_cpus as u64) as CpuId;
-            self.core.wake_task(task_id, wakeup_cpu);
+        let spec = group.spec;
+        let weight = self.core.ctx.task(parent).weight;
+        let deadline = job.absolute_deadline();
+        let attrs = job.job.attrs();
+        let cgroup = self.core.ensure_cgroup(&job.job.cgroup);
+        let block_at = spec
+            .barrier_interval
+            .filter(|&interval| interval < spec.child_run_time);
 
-            self.job_cursor += 1;
+        for _ in 0..spec.children {
+            let child =
+                self.core
+                    .create_task(spec.child_run_time, weight, deadline, cgroup, attrs.clone());
+            self.core.ctx.task_mut(child).block_at = block_at;
+            self.fork_parents.insert(child, parent);
+            self.core.wake_task_by(child, parent, SCX_WAKE_FORK);
         }
     }
 
+    fn child_at_barrier(&mut self, parent: TaskId, child: TaskId) {
+        let group = self.forks.get_mut(&parent).expect("Child has no group");
+        group.at_barrier.push(child);
+        self.release_barrier(parent, child);
+    }
+
+    fn child_exit(&mut self, parent: TaskId, child: TaskId) {
+        let group = self.forks.get_mut(&parent).expect("Child has no group");
+        group.live -= 1;
+        if group.live == 0 {
+            // Join: the last child to exit wakes the parent
+            self.core.wake_task_by(parent, child, SCX_WAKE_TTWU);
+        } else {
+            self.release_barrier(parent, child);
+        }
+    }
+
+    // Once every live child is waiting, the last to arrive wakes the others
+    fn release_barrier(&mut self, parent: TaskId, last: TaskId) {
+        let group = self.forks.get_mut(&parent).expect("Child has no group");
+        if group.at_barrier.is_empty() || group.at_barrier.len() as u64 != group.live {
+            return;
+        }
+        group.barriers += 1;
+        let interval = group
+            .spec
+            .barrier_interval
+            .expect("Barrier without interval");
+        let next = (group.barriers + 1) * interval;
+        let block_at = (next < group.spec.child_run_time).then_some(next);
+
+        for child in std::mem::take(&mut group.at_barrier) {
+            self.core.ctx.task_mut(child).block_at = block_at;
+            if child == last {
+                let cpu = self.core.ctx.task(last).last_cpu.expect("Child never ran");
+                self.core.wake_task(child, cpu);
+            } else {
+                self.core.wake_task_by(child, last, SCX_WAKE_TTWU);
+            }
+        }
+    }
+
+    // Never true for an unbounded source
     pub fn all_jobs_completed(&self) -> bool {
-        self.num_jobs_complete == self.jobs.len()
+        self.source.is_none()
+            && self.next_job.is_none()
+            && self.live.is_empty()
+            && self.pending.is_empty()
+            && self.wakees.is_empty()
+            && self.submissions.is_empty()
+    }
+
+    pub fn jobs_completed(&self) -> usize {
+        self.num_jobs_complete
+    }
+
+    pub fn deadline_misses(&self) -> usize {
+        self.num_deadline_misses
+    }
+
+    // Times a lock holder was descheduled while runnable with a heavier waiter
+    pub fn lock_holder_preemptions(&self) -> usize {
+        self.num_lock_holder_preemptions
     }
 
     pub fn jobs_map<T>(&self, f: impl FnMut(&JobInstance) -> T) -> impl Iterator<Item = f64>
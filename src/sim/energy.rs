@@ -0,0 +1,138 @@
+use crate::core::{CpuActivity, CpuId, SCX_CPUPERF_ONE};
+
+// Power is in arbitrary units per tick, so that energy is power integrated
+// over ticks
+pub trait EnergyModel {
+    // Power drawn by an online CPU during a busy tick at performance level `perf`
+    fn busy_power(&self, cpu: CpuId, perf: u64) -> f64;
+
+    // Power drawn by an idle CPU, which may be in one of its idle states
+    fn idle_power(&self, cpu: CpuId, idle_state: Option<usize>) -> f64;
+
+    // Energy spent bringing an idle CPU back to work
+    fn wakeup_energy(&self, cpu: CpuId) -> f64;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerfState {
+    pub perf: u64,
+    pub power: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuEnergy {
+    // Ascending by perf; the last state must reach SCX_CPUPERF_ONE
+    pub perf_states: Vec<PerfState>,
+    // Power when idle but in no idle state, and in each of the CPU's idle states
+    pub idle_power: f64,
+    pub idle_state_power: Vec<f64>,
+    pub wakeup_energy: f64,
+}
+
+// Table-driven model, like the kernel's Energy Model: a busy CPU draws the
+// power of the lowest performance state at or above its current level
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerfStateEnergyModel {
+    pub cpus: Vec<CpuEnergy>,
+}
+
+impl PerfStateEnergyModel {
+    pub fn new(cpus: Vec<CpuEnergy>) -> Self {
+        for (id, cpu) in cpus.iter().enumerate() {
+            assert!(
+                cpu.perf_states.is_sorted_by_key(|state| state.perf),
+                "Performance states of CPU {id} must be ascending"
+            );
+            assert!(
+                cpu.perf_states
+                    .last()
+                    .is_some_and(|state| state.perf >= SCX_CPUPERF_ONE),
+                "Performance states of CPU {id} must reach SCX_CPUPERF_ONE"
+            );
+        }
+        Self { cpus }
+    }
+
+    pub fn uniform(num_cpus: usize, cpu: CpuEnergy) -> Self {
+        Self::new(vec![cpu; num_cpus])
+    }
+}
+
+impl EnergyModel for PerfStateEnergyModel {
+    fn busy_power(&self, cpu: CpuId, perf: u64) -> f64 {
+        let states = &self.cpus[cpu].perf_states;
+        states
+            .iter()
+            .find(|state| state.perf >= perf)
+            .unwrap_or(&states[states.len() - 1])
+            .power
+    }
+
+    fn idle_power(&self, cpu: CpuId, idle_state: Option<usize>) -> f64 {
+        let cpu = &self.cpus[cpu];
+        idle_state
+            .and_then(|state| cpu.idle_state_power.get(state))
+            .copied()
+            .unwrap_or(cpu.idle_power)
+    }
+
+    fn wakeup_energy(&self, cpu: CpuId) -> f64 {
+        self.cpus[cpu].wakeup_energy
+    }
+}
+
+// Integrates a model over per-tick CPU activity
+pub(crate) struct EnergyMeter {
+    model: Box<dyn EnergyModel>,
+    was_busy: Vec<bool>,
+    pub total: f64,
+    // Idle power and wakeups, which no single job is responsible for
+    pub idle: f64,
+    // Busy power while no task made progress, e.g. with a higher class running
+    pub stolen: f64,
+}
+
+impl EnergyMeter {
+    pub fn new(model: Box<dyn EnergyModel>, num_cpus: usize) -> Self {
+        Self {
+            model,
+            was_busy: vec![false; num_cpus],
+            total: 0.0,
+            idle: 0.0,
+            stolen: 0.0,
+        }
+    }
+
+    // Returns the energy attributable to the task on each CPU
+    pub fn record(&mut self, activity: &[CpuActivity]) -> Vec<f64> {
+        activity
+            .iter()
+            .enumerate()
+            .map(|(cpu, activity)| {
+                let was_busy = std::mem::replace(&mut self.was_busy[cpu], activity.busy);
+                if !activity.online {
+                    return 0.0;
+                }
+                if !activity.busy {
+                    let energy = self.model.idle_power(cpu, activity.idle_state);
+                    self.total += energy;
+                    self.idle += energy;
+                    return 0.0;
+                }
+                if !was_busy {
+                    let energy = self.model.wakeup_energy(cpu);
+                    self.total += energy;
+                    self.idle += energy;
+                }
+                let energy = self.model.busy_power(cpu, activity.perf);
+                self.total += energy;
+                if activity.ran.is_some() {
+                    energy
+                } else {
+                    self.stolen += energy;
+                    0.0
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,24 @@
+use super::job::JobId;
+use crate::core::{CpuId, HigherClass, Ticks};
+
+// Scripted changes, applied at the start of the tick they are scheduled for
+#[derive(Debug, Clone)]
+pub enum SimEvent {
+    // Reweight a job's live tasks and its future releases
+    Renice {
+        job: JobId,
+        nice: i32,
+    },
+    // Higher-class activity on `cpu`, unless it is offline; see SchedCore::steal_cpu
+    Interference {
+        cpu: CpuId,
+        class: HigherClass,
+        duration: Ticks,
+    },
+    CpuOffline {
+        cpu: CpuId,
+    },
+    CpuOnline {
+        cpu: CpuId,
+    },
+}
@@ -0,0 +1,147 @@
+use rustc_hash::FxHashMap;
+
+use crate::core::{CGROUP_WEIGHT_DFL, CgroupId, KernelCtx, NICE_0_WEIGHT, TaskState, Ticks};
+
+// CPU time a cgroup and its descendants should have had under a truly
+// hierarchical scheduler, and what they got
+#[derive(Debug, Clone, PartialEq)]
+pub struct CgroupShare {
+    pub path: String,
+    pub ideal: f64,
+    pub actual: Ticks,
+}
+
+impl CgroupShare {
+    // Positive when the cgroup got more than its share
+    pub fn error(&self) -> f64 {
+        self.actual as f64 - self.ideal
+    }
+}
+
+// Fluid model of hierarchical weighting, as CFS group scheduling does it, for
+// measuring how far a scheduler (e.g. flatcg) strays from it. Each tick the
+// available CPUs are split top-down: a cgroup's share goes to its runnable
+// tasks and active children in proportion to their weights, with nothing
+// getting more CPUs than it has runnable tasks. cpu.max limits are ignored.
+#[derive(Debug, Default)]
+pub struct CgroupFairness {
+    shares: FxHashMap<CgroupId, CgroupShare>,
+    // Ideal shares for the tick about to run, credited once it has
+    pending: FxHashMap<CgroupId, f64>,
+    // cgroup.usage as of the last record()
+    usage: FxHashMap<CgroupId, Ticks>,
+}
+
+impl CgroupFairness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Call before the first step and after every step, so each tick is judged
+    // on the tasks runnable as it starts
+    pub fn record(&mut self, ctx: &KernelCtx) {
+        for (cgroup, ideal) in self.pending.drain() {
+            if let Some(share) = self.shares.get_mut(&cgroup) {
+                share.ideal += ideal;
+            }
+        }
+        for (id, cgroup) in &ctx.cgroups {
+            let share = self.shares.entry(id).or_insert_with(|| CgroupShare {
+                path: cgroup.path.clone(),
+                ideal: 0.0,
+                actual: 0,
+            });
+            let seen = self.usage.insert(id, cgroup.usage).unwrap_or(cgroup.usage);
+            share.actual += cgroup.usage - seen;
+        }
+
+        let mut tasks: FxHashMap<CgroupId, Vec<u64>> = FxHashMap::default();
+        for task in ctx.tasks.values() {
+            if matches!(task.state, TaskState::Runnable | TaskState::Running) {
+                tasks.entry(task.cgroup).or_default().push(task.weight);
+            }
+        }
+        let mut demand: FxHashMap<CgroupId, usize> = FxHashMap::default();
+        for (&cgroup, weights) in &tasks {
+            for id in ctx.cgroup_ancestors(cgroup) {
+                *demand.entry(id).or_default() += weights.len();
+            }
+        }
+
+        let cpus = ctx
+            .cpus
+            .iter()
+            .filter(|cpu| cpu.online && cpu.stolen.is_none())
+            .count();
+        let root = ctx.root_cgroup();
+        let share = demand.get(&root).copied().unwrap_or(0).min(cpus) as f64;
+        self.split(ctx, root, share, &tasks, &demand);
+    }
+
+    // Every cgroup seen, removed ones included, by path
+    pub fn shares(&self) -> Vec<CgroupShare> {
+        let mut shares: Vec<CgroupShare> = self.shares.values().cloned().collect();
+        shares.sort_by(|a, b| a.path.cmp(&b.path));
+        shares
+    }
+
+    fn split(
+        &mut self,
+        ctx: &KernelCtx,
+        cgroup: CgroupId,
+        share: f64,
+        tasks: &FxHashMap<CgroupId, Vec<u64>>,
+        demand: &FxHashMap<CgroupId, usize>,
+    ) {
+        *self.pending.entry(cgroup).or_default() += share;
+
+        // (weight, CPUs it can use, child cgroup); cpu.weight is scaled to
+        // task weights, as a default cgroup weighs the same as a nice 0 task
+        let mut entities: Vec<(u64, f64, Option<CgroupId>)> = tasks
+            .get(&cgroup)
+            .into_iter()
+            .flatten()
+            .map(|&weight| (weight, 1.0, None))
+            .collect();
+        for &child in &ctx.cgroup(cgroup).children {
+            if let Some(&n) = demand.get(&child) {
+                let weight = ctx.cgroup(child).weight * NICE_0_WEIGHT / CGROUP_WEIGHT_DFL;
+                entities.push((weight, n as f64, Some(child)));
+            }
+        }
+
+        // Water-filling: entities that cannot use their proportional share
+        // get what they can use, and the rest is split again among the others
+        let mut left = share;
+        let mut given = vec![None; entities.len()];
+        loop {
+            let weight: u64 = (0..entities.len())
+                .filter(|&i| given[i].is_none())
+                .map(|i| entities[i].0)
+                .sum();
+            let mut capped = false;
+            for i in 0..entities.len() {
+                let (w, cap, _) = entities[i];
+                if given[i].is_none() && cap <= left * w as f64 / weight as f64 {
+                    given[i] = Some(cap);
+                    left -= cap;
+                    capped = true;
+                }
+            }
+            if !capped || weight == 0 {
+                break;
+            }
+        }
+        let weight: u64 = (0..entities.len())
+            .filter(|&i| given[i].is_none())
+            .map(|i| entities[i].0)
+            .sum();
+
+        for (i, &(w, _, child)) in entities.iter().enumerate() {
+            let share = given[i].unwrap_or_else(|| left * w as f64 / weight as f64);
+            if let Some(child) = child {
+                self.split(ctx, child, share, tasks, demand);
+            }
+        }
+    }
+}
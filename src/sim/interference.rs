@@ -0,0 +1,61 @@
+use rand::prelude::*;
+use rand_distr::Exp;
+
+use super::event::SimEvent;
+use crate::core::{HigherClass, Ticks};
+
+// Random higher-class activity: on every CPU and tick, a burst of `class`
+// starts with probability `rate`, lasting an exponentially distributed
+// number of ticks (at least one). Bursts starting on an offline CPU are
+// dropped by Sim.
+#[derive(Debug, Clone)]
+pub struct RandomInterference {
+    pub seed: u64,
+    pub class: HigherClass,
+    pub rate: f64,
+    pub mean_duration: f64,
+}
+
+impl RandomInterference {
+    pub fn new(seed: u64, class: HigherClass, rate: f64, mean_duration: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&rate),
+            "Interference rate {rate} must be a probability"
+        );
+        assert!(
+            mean_duration.is_finite() && mean_duration > 0.0,
+            "Interference mean_duration {mean_duration} must be positive"
+        );
+        Self {
+            seed,
+            class,
+            rate,
+            mean_duration,
+        }
+    }
+
+    pub fn generate(&self, num_cpus: usize, horizon: Ticks) -> Vec<(Ticks, SimEvent)> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(self.seed);
+        let exp = Exp::new(1.0 / self.mean_duration).expect("invalid mean_duration");
+        let mut events = Vec::new();
+
+        for t in 0..horizon {
+            for cpu in 0..num_cpus {
+                if !rng.random_bool(self.rate) {
+                    continue;
+                }
+                let duration = (exp.sample(&mut rng).ceil() as Ticks).max(1);
+                events.push((
+                    t,
+                    SimEvent::Interference {
+                        cpu,
+                        class: self.class,
+                        duration,
+                    },
+                ));
+            }
+        }
+
+        events
+    }
+}
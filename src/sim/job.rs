@@ -1,4 +1,9 @@
-use crate::core::state::Ticks;
+use super::client::ClientId;
+use crate::core::{
+    lock::CriticalSection,
+    prio::{NICE_0_WEIGHT, nice_to_weight},
+    state::{TaskAttrs, Ticks},
+};
 
 pub type JobId = u64;
 
@@ -7,13 +12,131 @@ pub struct Job {
     pub id: JobId,
     pub arrival_time: Ticks,
     pub run_time: Ticks,
-    // Range: 1..10000
+    // Load weight, on the scale of sched_prio_to_weight. Ignored when nice is set.
     pub weight: u64,
+    pub nice: Option<i32>,
+    // Relative to arrival_time (of each release, for recurring jobs)
+    pub deadline: Option<Ticks>,
+    pub period: Option<Period>,
+    // Passed to the scheduler as TaskAttrs
+    pub name: String,
+    pub tags: Vec<String>,
+    // Path such as "/a/b"; missing cgroups are created on arrival. Empty means the root.
+    pub cgroup: String,
+    // Released by a wakeup from this job when the same release completes,
+    // rather than at arrival_time
+    pub woken_by: Option<JobId>,
+    // Woken at arrival_time by this job's task, when it is live then, so
+    // placed as that wakeup would be. Otherwise woken as from idle.
+    pub waker: Option<JobId>,
+    // Whether wakeups by woken_by or waker pass SCX_WAKE_SYNC, hinting that
+    // the waker is about to sleep
+    pub sync_wakeup: bool,
+    pub fork: Option<ForkJoin>,
+    // In order of start, non-overlapping, and within run_time. Locks are
+    // created through SchedCore::create_lock.
+    pub critical_sections: Vec<CriticalSection>,
+}
+
+// Recurring jobs are released every `interval` ticks, starting at `arrival_time`.
+#[derive(Debug, Clone, Copy)]
+pub struct Period {
+    pub interval: Ticks,
+    // Total number of releases, including the first
+    pub releases: u64,
+}
+
+// The job's task runs `at` ticks, forks `children` tasks and sleeps until all
+// of them exit, then runs the rest of its run_time. The job completes with the
+// parent task.
+#[derive(Debug, Clone, Copy)]
+pub struct ForkJoin {
+    pub at: Ticks,
+    pub children: u64,
+    pub child_run_time: Ticks,
+    // Children wait for each other every `barrier_interval` ticks of service
+    pub barrier_interval: Option<Ticks>,
 }
 
 #[derive(Debug, Clone)]
 pub struct JobInstance {
     pub job: Job,
+    // Index of this release for recurring jobs, 0 otherwise
+    pub release: u64,
     pub start_time: Option<Ticks>,
     pub completion_time: Option<Ticks>,
+    // completion_time - absolute deadline; negative when finished early
+    pub lateness: Option<i64>,
+    // Busy CPU energy spent running the job, if an energy model is set
+    pub energy: f64,
+    pub migrations: u64,
+    // Time spent waiting for locks
+    pub lock_wait: Ticks,
+    // Submitted by a closed-loop client; `release` counts its submissions
+    pub client: Option<ClientId>,
+}
+
+impl Default for Job {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            arrival_time: 0,
+            run_time: 0,
+            weight: NICE_0_WEIGHT,
+            nice: None,
+            deadline: None,
+            period: None,
+            name: String::new(),
+            tags: Vec::new(),
+            cgroup: String::new(),
+            woken_by: None,
+            waker: None,
+            sync_wakeup: true,
+            fork: None,
+            critical_sections: Vec::new(),
+        }
+    }
+}
+
+impl Job {
+    pub fn releases(&self) -> u64 {
+        self.period.map_or(1, |period| period.releases)
+    }
+
+    pub fn task_weight(&self) -> u64 {
+        self.nice.map_or(self.weight, nice_to_weight)
+    }
+
+    pub fn attrs(&self) -> TaskAttrs {
+        TaskAttrs {
+            name: self.name.clone(),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+impl JobInstance {
+    pub fn new(job: Job, release: u64) -> Self {
+        Self {
+            job,
+            release,
+            start_time: None,
+            completion_time: None,
+            lateness: None,
+            energy: 0.0,
+            migrations: 0,
+            lock_wait: 0,
+            client: None,
+        }
+    }
+
+    pub fn absolute_deadline(&self) -> Option<Ticks> {
+        self.job
+            .deadline
+            .map(|deadline| self.job.arrival_time + deadline)
+    }
+
+    pub fn missed_deadline(&self) -> bool {
+        self.lateness.is_some_and(|lateness| lateness > 0)
+    }
 }
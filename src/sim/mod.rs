@@ -1,5 +1,19 @@
+pub mod client;
 pub mod driver;
+pub mod energy;
+pub mod event;
+pub mod fairness;
+pub mod interference;
 pub mod job;
+pub mod source;
+pub mod workload;
 
+pub use client::{Client, ClientId, ThinkTime};
 pub use driver::Sim;
-pub use job::{Job, JobId, JobInstance};
+pub use energy::{CpuEnergy, EnergyModel, PerfState, PerfStateEnergyModel};
+pub use event::SimEvent;
+pub use fairness::{CgroupFairness, CgroupShare};
+pub use interference::RandomInterference;
+pub use job::{ForkJoin, Job, JobId, JobInstance, Period};
+pub use source::{JobSink, JobSource};
+pub use workload::{Arrivals, JobClass, MmppState, ServiceTime, Workload, WorkloadJobs};
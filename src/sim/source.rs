@@ -0,0 +1,26 @@
+use super::job::{Job, JobInstance};
+
+// Jobs in order of arrival_time, pulled lazily as simulated time reaches
+// them. A job woken by another (woken_by) is pulled as soon as it comes up,
+// and must come up after that job and before it completes; its arrival_time
+// is ignored. Otherwise it is released on its own once pulled.
+pub trait JobSource {
+    fn next_job(&mut self) -> Option<Job>;
+}
+
+impl<I: Iterator<Item = Job>> JobSource for I {
+    fn next_job(&mut self) -> Option<Job> {
+        self.next()
+    }
+}
+
+// Receives each job once it completes
+pub trait JobSink {
+    fn complete(&mut self, job: JobInstance);
+}
+
+impl<F: FnMut(JobInstance)> JobSink for F {
+    fn complete(&mut self, job: JobInstance) {
+        self(job)
+    }
+}
@@ -0,0 +1,333 @@
+use std::f64::consts::TAU;
+
+use rand::{distr::weighted::WeightedIndex, prelude::*};
+use rand_distr::{Exp, LogNormal, Pareto, Poisson};
+use serde::Deserialize;
+
+use super::job::{Job, JobId};
+use crate::core::Ticks;
+
+// How many jobs arrive at each tick
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum Arrivals {
+    // Poisson number of arrivals per tick, `rate` on average
+    Poisson {
+        rate: f64,
+    },
+    // One arrival every `interval` ticks, starting at tick 0
+    Deterministic {
+        interval: Ticks,
+    },
+    // Markov-modulated Poisson: cycles through `states`, leaving each with
+    // probability 1/mean_dwell per tick
+    Mmpp {
+        states: Vec<MmppState>,
+    },
+    // Poisson with rate * (1 + amplitude * sin(2πt / period)), amplitude in 0..=1
+    Diurnal {
+        rate: f64,
+        amplitude: f64,
+        period: Ticks,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MmppState {
+    pub rate: f64,
+    pub mean_dwell: f64,
+}
+
+// Job run times, rounded up to a whole, nonzero number of ticks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ServiceTime {
+    Fixed(Ticks),
+    // Inclusive of both ends
+    Uniform { min: Ticks, max: Ticks },
+    Exponential { mean: f64 },
+    // Parameters of the underlying normal distribution
+    LogNormal { mu: f64, sigma: f64 },
+    Pareto { scale: f64, shape: f64 },
+    // Drawn uniformly from observed run times
+    Empirical(Vec<Ticks>),
+    // Components chosen in proportion to their weights
+    Mixture(Vec<(f64, ServiceTime)>),
+}
+
+// One kind of job in the mix. `job` is the template for everything but id,
+// arrival_time and run_time.
+#[derive(Debug, Clone)]
+pub struct JobClass {
+    // Relative to the other classes
+    pub share: f64,
+    pub job: Job,
+    pub service: ServiceTime,
+}
+
+// Seeded synthetic workload. Jobs are numbered from 0 in arrival order.
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub seed: u64,
+    // No arrivals at or after this tick; None for an unbounded workload
+    pub horizon: Option<Ticks>,
+    pub arrivals: Arrivals,
+    pub classes: Vec<JobClass>,
+}
+
+impl Arrivals {
+    pub fn validate(&self) -> Result<(), String> {
+        let rate = |rate: f64| {
+            if rate.is_finite() && rate >= 0.0 {
+                Ok(())
+            } else {
+                Err(format!("rate {rate} must be finite and non-negative"))
+            }
+        };
+        match self {
+            Arrivals::Poisson { rate: r } => rate(*r),
+            Arrivals::Deterministic { interval: 0 } => Err("interval must be nonzero".to_string()),
+            Arrivals::Deterministic { .. } => Ok(()),
+            Arrivals::Mmpp { states } if states.is_empty() => {
+                Err("at least one state is required".to_string())
+            }
+            Arrivals::Mmpp { states } => states.iter().enumerate().try_for_each(|(i, state)| {
+                rate(state.rate).map_err(|msg| format!("states[{i}]: {msg}"))?;
+                if state.mean_dwell >= 1.0 {
+                    Ok(())
+                } else {
+                    Err(format!("states[{i}]: mean_dwell must be at least 1"))
+                }
+            }),
+            Arrivals::Diurnal {
+                rate: r,
+                amplitude,
+                period,
+            } => {
+                rate(*r)?;
+                if !(0.0..=1.0).contains(amplitude) {
+                    return Err(format!("amplitude {amplitude} must be within 0..=1"));
+                }
+                if *period == 0 {
+                    return Err("period must be nonzero".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Whether no job ever arrives, so an unbounded workload would never yield one
+    fn never_arrives(&self) -> bool {
+        match self {
+            Arrivals::Poisson { rate } | Arrivals::Diurnal { rate, .. } => *rate == 0.0,
+            Arrivals::Deterministic { .. } => false,
+            Arrivals::Mmpp { states } => states.iter().all(|state| state.rate == 0.0),
+        }
+    }
+}
+
+impl ServiceTime {
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |name: &str, value: f64| {
+            if value.is_finite() && value > 0.0 {
+                Ok(())
+            } else {
+                Err(format!("{name} {value} must be positive"))
+            }
+        };
+        match self {
+            ServiceTime::Fixed(0) => Err("run time must be nonzero".to_string()),
+            ServiceTime::Fixed(_) => Ok(()),
+            ServiceTime::Uniform { min, max } if min > max => {
+                Err(format!("min {min} exceeds max {max}"))
+            }
+            ServiceTime::Uniform { .. } => Ok(()),
+            ServiceTime::Exponential { mean } => positive("mean", *mean),
+            ServiceTime::LogNormal { mu, sigma } => {
+                if !mu.is_finite() {
+                    return Err(format!("mu {mu} must be finite"));
+                }
+                if sigma.is_finite() && *sigma >= 0.0 {
+                    Ok(())
+                } else {
+                    Err(format!("sigma {sigma} must be finite and non-negative"))
+                }
+            }
+            ServiceTime::Pareto { scale, shape } => {
+                positive("scale", *scale)?;
+                positive("shape", *shape)
+            }
+            ServiceTime::Empirical(samples) if samples.is_empty() => {
+                Err("at least one sample is required".to_string())
+            }
+            ServiceTime::Empirical(_) => Ok(()),
+            ServiceTime::Mixture(components) if components.is_empty() => {
+                Err("at least one component is required".to_string())
+            }
+            ServiceTime::Mixture(components) => {
+                components
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(i, (weight, component))| {
+                        positive("weight", *weight).map_err(|msg| format!("[{i}]: {msg}"))?;
+                        component.validate().map_err(|msg| format!("[{i}]: {msg}"))
+                    })
+            }
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> Ticks {
+        let ticks = match self {
+            ServiceTime::Fixed(ticks) => return (*ticks).max(1),
+            ServiceTime::Uniform { min, max } => return rng.random_range(*min..=*max).max(1),
+            ServiceTime::Empirical(samples) => {
+                return samples
+                    .choose(rng)
+                    .copied()
+                    .expect("empty empirical distribution")
+                    .max(1);
+            }
+            ServiceTime::Mixture(components) => {
+                let index = WeightedIndex::new(components.iter().map(|(weight, _)| *weight))
+                    .expect("invalid mixture weights")
+                    .sample(rng);
+                return components[index].1.sample(rng);
+            }
+            ServiceTime::Exponential { mean } => Exp::new(1.0 / mean)
+                .expect("invalid exponential mean")
+                .sample(rng),
+            ServiceTime::LogNormal { mu, sigma } => LogNormal::new(*mu, *sigma)
+                .expect("invalid lognormal parameters")
+                .sample(rng),
+            ServiceTime::Pareto { scale, shape } => Pareto::new(*scale, *shape)
+                .expect("invalid Pareto parameters")
+                .sample(rng),
+        };
+        (ticks.ceil() as Ticks).max(1)
+    }
+}
+
+impl Workload {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.classes.is_empty() {
+            return Err("at least one class is required".to_string());
+        }
+        self.arrivals
+            .validate()
+            .map_err(|msg| format!("arrivals: {msg}"))?;
+        if self.horizon.is_none() && self.arrivals.never_arrives() {
+            return Err("an unbounded workload needs a nonzero arrival rate".to_string());
+        }
+        self.classes.iter().enumerate().try_for_each(|(i, class)| {
+            if !(class.share.is_finite() && class.share > 0.0) {
+                return Err(format!("classes[{i}].share must be positive"));
+            }
+            class
+                .service
+                .validate()
+                .map_err(|msg| format!("classes[{i}].service: {msg}"))
+        })
+    }
+
+    // Lazily generated, so usable as a JobSource even without a horizon.
+    // Panics if the workload does not validate.
+    pub fn jobs(&self) -> WorkloadJobs {
+        if let Err(msg) = self.validate() {
+            panic!("Invalid workload: {msg}");
+        }
+        let class_index = WeightedIndex::new(self.classes.iter().map(|class| class.share))
+            .expect("validated class shares");
+
+        WorkloadJobs {
+            workload: self.clone(),
+            rng: StdRng::seed_from_u64(self.seed),
+            class_index,
+            now: 0,
+            due: 0,
+            next_id: 0,
+            mmpp_state: 0,
+        }
+    }
+
+    pub fn generate(&self) -> Vec<Job> {
+        assert!(
+            self.horizon.is_some(),
+            "Cannot generate an unbounded workload"
+        );
+        self.jobs().collect()
+    }
+}
+
+pub struct WorkloadJobs {
+    workload: Workload,
+    rng: StdRng,
+    class_index: WeightedIndex<f64>,
+    // Tick being generated, and jobs left to arrive during it
+    now: Ticks,
+    due: u64,
+    next_id: JobId,
+    mmpp_state: usize,
+}
+
+impl WorkloadJobs {
+    fn poisson(&mut self, rate: f64) -> u64 {
+        if rate <= 0.0 {
+            return 0;
+        }
+        let poisson = Poisson::new(rate).expect("invalid arrival rate");
+        poisson.sample(&mut self.rng) as u64
+    }
+
+    fn arrivals_at(&mut self, t: Ticks) -> u64 {
+        match &self.workload.arrivals {
+            Arrivals::Poisson { rate } => self.poisson(*rate),
+            Arrivals::Deterministic { interval } => t.is_multiple_of(*interval) as u64,
+            Arrivals::Mmpp { states } => {
+                let state = states[self.mmpp_state];
+                let next = (self.mmpp_state + 1) % states.len();
+                if self.rng.random_bool(1.0 / state.mean_dwell) {
+                    self.mmpp_state = next;
+                }
+                self.poisson(state.rate)
+            }
+            &Arrivals::Diurnal {
+                rate,
+                amplitude,
+                period,
+            } => {
+                let phase = TAU * t as f64 / period as f64;
+                self.poisson(rate * (1.0 + amplitude * phase.sin()))
+            }
+        }
+    }
+}
+
+impl Iterator for WorkloadJobs {
+    type Item = Job;
+
+    fn next(&mut self) -> Option<Job> {
+        while self.due == 0 {
+            if self
+                .workload
+                .horizon
+                .is_some_and(|horizon| self.now >= horizon)
+            {
+                return None;
+            }
+            self.due = self.arrivals_at(self.now);
+            self.now += 1;
+        }
+        self.due -= 1;
+
+        let class = &self.workload.classes[self.class_index.sample(&mut self.rng)];
+        let job = Job {
+            id: self.next_id,
+            arrival_time: self.now - 1,
+            run_time: class.service.sample(&mut self.rng),
+            ..class.job.clone()
+        };
+        self.next_id += 1;
+        Some(job)
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::{self, Write};
+
+use rustc_hash::FxHashMap;
+use serde_json::{Value, json};
+use slotmap::Key;
+
+use crate::core::{CpuId, DsqId, KernelCtx, SchedCoreEvent, TaskId, Ticks};
+
+// Chrome trace-event processes: CPUs hold task slices, DSQs queue depth counters
+const CPUS_PID: u64 = 0;
+const DSQS_PID: u64 = 1;
+
+// Builds Chrome trace-event JSON, as opened by Perfetto or chrome://tracing:
+// a track per CPU with a slice for every stint a task runs, instants for
+// wakeups and preemptions, and a queue depth counter per DSQ
+pub struct ChromeTrace {
+    tick_ns: u64,
+    events: Vec<Value>,
+    // Task on each CPU and when it got there
+    running: Vec<Option<(TaskId, Ticks)>>,
+    // Last depth written for each DSQ
+    depths: FxHashMap<DsqId, usize>,
+}
+
+impl ChromeTrace {
+    pub fn new(tick_ns: u64) -> Self {
+        Self {
+            tick_ns,
+            events: vec![
+                json!({"ph": "M", "name": "process_name", "pid": CPUS_PID, "args": {"name": "CPUs"}}),
+                json!({"ph": "M", "name": "process_name", "pid": DSQS_PID, "args": {"name": "DSQs"}}),
+            ],
+            running: Vec::new(),
+            depths: FxHashMap::default(),
+        }
+    }
+
+    pub fn record(&mut self, events: &[(Ticks, SchedCoreEvent)], ctx: &KernelCtx) {
+        for cpu in self.running.len()..ctx.cpus.len() {
+            self.events.extend([
+                json!({"ph": "M", "name": "thread_name", "pid": CPUS_PID, "tid": cpu, "args": {"name": format!("CPU {cpu}")}}),
+                json!({"ph": "M", "name": "thread_sort_index", "pid": CPUS_PID, "tid": cpu, "args": {"sort_index": cpu}}),
+            ]);
+            self.running.push(None);
+        }
+
+        for (at, event) in events {
+            match *event {
+                SchedCoreEvent::CpuCurrentChange { cpu, from, to } => {
+                    if from.is_some() {
+                        self.end_slice(cpu, *at, ctx);
+                    }
+                    if let Some(next) = to {
+                        self.running[cpu] = Some((next, *at));
+                    }
+                }
+                SchedCoreEvent::TaskPreempted { task, cpu } => {
+                    let name = format!("preempt {}", task_name(ctx, task));
+                    self.instant(*at, cpu, name, json!({"task": task}));
+                }
+                SchedCoreEvent::TaskWakeup { task, waker, cpu } => {
+                    let name = format!("wakeup {}", task_name(ctx, task));
+                    self.instant(*at, cpu, name, json!({"task": task, "waker": waker}));
+                }
+                _ => {}
+            }
+        }
+
+        // Depths are sampled once per step, and written when they change
+        let now = ctx.now;
+        let mut gone: Vec<DsqId> = self.depths.keys().copied().collect();
+        for dsq in ctx.dsqs.keys() {
+            gone.retain(|&d| d != dsq);
+            let depth = ctx.dsq_nr_queued(dsq);
+            if self.depths.insert(dsq, depth) != Some(depth) {
+                self.counter(now, dsq_name(ctx, dsq), depth);
+            }
+        }
+        for dsq in gone {
+            self.depths.remove(&dsq);
+            self.counter(now, dsq_name(ctx, dsq), 0);
+        }
+    }
+
+    // Closes slices still running at ctx.now
+    pub fn finish(mut self, ctx: &KernelCtx) -> Value {
+        for cpu in 0..self.running.len() {
+            self.end_slice(cpu, ctx.now, ctx);
+        }
+        json!({"traceEvents": self.events, "displayTimeUnit": "ms"})
+    }
+
+    pub fn write(self, ctx: &KernelCtx, out: impl Write) -> io::Result<()> {
+        serde_json::to_writer(out, &self.finish(ctx)).map_err(io::Error::from)
+    }
+
+    // Chrome timestamps are in microseconds
+    fn ts(&self, at: Ticks) -> f64 {
+        (at * self.tick_ns) as f64 / 1000.0
+    }
+
+    fn end_slice(&mut self, cpu: CpuId, at: Ticks, ctx: &KernelCtx) {
+        let Some((task, start)) = self.running[cpu].take() else {
+            return;
+        };
+        let weight = ctx.tasks.get(&task).map(|t| t.weight);
+        self.events.push(json!({
+            "ph": "X",
+            "name": task_name(ctx, task),
+            "cat": "task",
+            "pid": CPUS_PID,
+            "tid": cpu,
+            "ts": self.ts(start),
+            "dur": self.ts(at) - self.ts(start),
+            "args": {"task": task, "weight": weight},
+        }));
+    }
+
+    fn instant(&mut self, at: Ticks, cpu: CpuId, name: String, args: Value) {
+        self.events.push(json!({
+            "ph": "i",
+            "s": "t",
+            "name": name,
+            "pid": CPUS_PID,
+            "tid": cpu,
+            "ts": self.ts(at),
+            "args": args,
+        }));
+    }
+
+    fn counter(&mut self, at: Ticks, name: String, depth: usize) {
+        self.events.push(json!({
+            "ph": "C",
+            "name": name,
+            "pid": DSQS_PID,
+            "ts": self.ts(at),
+            "args": {"depth": depth},
+        }));
+    }
+}
+
+fn task_name(ctx: &KernelCtx, task: TaskId) -> String {
+    match ctx.tasks.get(&task).map(|t| t.attrs.name.as_str()) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => format!("task {task}"),
+    }
+}
+
+fn dsq_name(ctx: &KernelCtx, dsq: DsqId) -> String {
+    if dsq == ctx.global_dsq() {
+        return "global".to_string();
+    }
+    match (0..ctx.cpus.len()).find(|&cpu| ctx.per_cpu_dsq(cpu) == dsq) {
+        Some(cpu) => format!("CPU {cpu} local"),
+        None => format!("dsq {}", dsq.data().as_ffi() as u32),
+    }
+}
@@ -0,0 +1,221 @@
+use std::io::{self, Write};
+
+use rustc_hash::FxHashSet;
+
+use crate::core::{CpuId, KernelCtx, SchedCoreEvent, TaskId, TaskState, Ticks, weight_to_nice};
+
+// Writes a run as ftrace text, as read from /sys/kernel/tracing/trace, with
+// sched_switch, sched_wakeup(_new), sched_migrate_task and sched_process_exit
+// lines. Pids are TaskId + 1, leaving 0 for the idle task.
+pub struct FtraceWriter<W: Write> {
+    out: W,
+    tick_ns: u64,
+    // What each CPU is running, as of the events written so far
+    current: Vec<Option<TaskId>>,
+    woken: FxHashSet<TaskId>,
+    // Switches to idle held back, as (time, prev task info, prev_state), in
+    // case the CPU switches to a task at the same time. The two are written as
+    // one switch, as the kernel goes straight from one task to the other.
+    to_idle: Vec<Option<(Ticks, TaskInfo, &'static str)>>,
+}
+
+impl<W: Write> FtraceWriter<W> {
+    pub fn new(mut out: W, tick_ns: u64) -> io::Result<Self> {
+        writeln!(out, "# tracer: nop")?;
+        writeln!(out, "#")?;
+        writeln!(out, "#           TASK-PID     CPU#     TIMESTAMP  FUNCTION")?;
+        writeln!(out, "#              | |         |         |         |")?;
+        Ok(Self {
+            out,
+            tick_ns,
+            current: Vec::new(),
+            woken: FxHashSet::default(),
+            to_idle: Vec::new(),
+        })
+    }
+
+    pub fn write(&mut self, events: &[(Ticks, SchedCoreEvent)], ctx: &KernelCtx) -> io::Result<()> {
+        self.current.resize(ctx.cpus.len(), None);
+        self.to_idle.resize(ctx.cpus.len(), None);
+
+        for (i, (at, event)) in events.iter().enumerate() {
+            self.write_to_idle(|held| held < *at)?;
+            match *event {
+                SchedCoreEvent::CpuCurrentChange { cpu, from, to } => {
+                    let prev_state =
+                        from.map_or("R", |prev| match leaving_state(&events[i + 1..], prev) {
+                            TaskState::Blocked => "S",
+                            TaskState::Completed => "X",
+                            _ => "R+",
+                        });
+                    let prev = task_info(ctx, from, cpu);
+                    if prev_state == "X" {
+                        let fields = task_fields(ctx, from);
+                        self.line(*at, cpu, &prev, "sched_process_exit", &fields)?;
+                    }
+                    self.current[cpu] = to;
+                    if from.is_some() && to.is_none() {
+                        self.write_cpu_to_idle(cpu)?;
+                        self.to_idle[cpu] = Some((*at, prev, prev_state));
+                        continue;
+                    }
+
+                    // A task leaves its old CPU before it arrives on this one
+                    if let Some(to) = to {
+                        for other in 0..self.to_idle.len() {
+                            if other != cpu
+                                && self.to_idle[other]
+                                    .as_ref()
+                                    .is_some_and(|held| held.1.1 == to + 1)
+                            {
+                                self.write_cpu_to_idle(other)?;
+                            }
+                        }
+                    }
+                    let held = self.to_idle[cpu].take_if(|held| held.0 == *at && from.is_none());
+                    self.write_cpu_to_idle(cpu)?;
+                    let (prev, prev_state) = match held {
+                        Some((_, prev, prev_state)) => (prev, prev_state),
+                        None => (prev, prev_state),
+                    };
+                    self.switch(*at, cpu, prev, prev_state, task_info(ctx, to, cpu))?;
+                }
+                SchedCoreEvent::TaskWakeup { task, waker, cpu } => {
+                    // Reported on the waker's CPU when it is running, as the kernel does
+                    let on = waker
+                        .and_then(|waker| self.current.iter().position(|&t| t == Some(waker)))
+                        .unwrap_or(cpu);
+                    let name = if self.woken.insert(task) {
+                        "sched_wakeup_new"
+                    } else {
+                        "sched_wakeup"
+                    };
+                    let fields = format!("{} target_cpu={cpu:03}", task_fields(ctx, Some(task)));
+                    let owner = task_info(ctx, self.current[on], on);
+                    self.line(*at, on, &owner, name, &fields)?;
+                }
+                SchedCoreEvent::TaskMigrate { task, from, to } => {
+                    let fields = format!(
+                        "{} orig_cpu={from} dest_cpu={to}",
+                        task_fields(ctx, Some(task))
+                    );
+                    let owner = task_info(ctx, self.current[to], to);
+                    self.line(*at, to, &owner, "sched_migrate_task", &fields)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Writes out any switches to idle still held back
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.write_to_idle(|_| true)?;
+        Ok(self.out)
+    }
+
+    fn write_to_idle(&mut self, due: impl Fn(Ticks) -> bool) -> io::Result<()> {
+        for cpu in 0..self.to_idle.len() {
+            if self.to_idle[cpu].as_ref().is_some_and(|held| due(held.0)) {
+                self.write_cpu_to_idle(cpu)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_cpu_to_idle(&mut self, cpu: CpuId) -> io::Result<()> {
+        let Some((at, prev, prev_state)) = self.to_idle[cpu].take() else {
+            return Ok(());
+        };
+        self.switch(at, cpu, prev, prev_state, task_info_idle(cpu))
+    }
+
+    fn switch(
+        &mut self,
+        at: Ticks,
+        cpu: CpuId,
+        prev: TaskInfo,
+        prev_state: &str,
+        (next_comm, next_pid, next_prio): TaskInfo,
+    ) -> io::Result<()> {
+        let (prev_comm, prev_pid, prev_prio) = &prev;
+        let fields = format!(
+            "prev_comm={prev_comm} prev_pid={prev_pid} prev_prio={prev_prio} \
+             prev_state={prev_state} ==> next_comm={next_comm} next_pid={next_pid} \
+             next_prio={next_prio}"
+        );
+        self.line(at, cpu, &prev, "sched_switch", &fields)
+    }
+
+    // Attributed to the task running on `cpu`, as in the kernel's trace output
+    fn line(
+        &mut self,
+        at: Ticks,
+        cpu: CpuId,
+        (comm, pid, _): &TaskInfo,
+        name: &str,
+        fields: &str,
+    ) -> io::Result<()> {
+        let comm = if *pid == 0 { "<idle>" } else { comm };
+        let ns = at * self.tick_ns;
+        let (secs, frac) = (ns / 1_000_000_000, ns % 1_000_000_000);
+        // Microseconds, unless the tick length needs more precision
+        let timestamp = if self.tick_ns.is_multiple_of(1000) {
+            format!("{secs}.{:06}", frac / 1000)
+        } else {
+            format!("{secs}.{frac:09}")
+        };
+        writeln!(
+            self.out,
+            "{:>16}-{pid:<7} [{cpu:03}] {timestamp:>13}: {name}: {fields}",
+            comm
+        )
+    }
+}
+
+// What `task` goes to when it next leaves Running among `events`
+pub(crate) fn leaving_state(events: &[(Ticks, SchedCoreEvent)], task: TaskId) -> TaskState {
+    events
+        .iter()
+        .find_map(|(_, event)| match *event {
+            SchedCoreEvent::TaskStateChange {
+                task: t,
+                from: TaskState::Running,
+                to,
+                ..
+            } if t == task => Some(to),
+            _ => None,
+        })
+        .unwrap_or(TaskState::Runnable)
+}
+
+// (comm, pid, prio) of a task; the idle task is swapper/<cpu>, with pid 0
+type TaskInfo = (String, usize, i32);
+
+fn task_info(ctx: &KernelCtx, task: Option<TaskId>, cpu: CpuId) -> TaskInfo {
+    let Some(task) = task else {
+        return task_info_idle(cpu);
+    };
+    let Some(state) = ctx.tasks.get(&task) else {
+        return ("task".to_string(), task + 1, 120);
+    };
+    // Kernel comms are at most 15 characters, without spaces
+    let comm: String = match state.attrs.name.as_str() {
+        "" => "task".to_string(),
+        name => name
+            .chars()
+            .map(|c| if c.is_whitespace() { '_' } else { c })
+            .take(15)
+            .collect(),
+    };
+    (comm, task + 1, 120 + weight_to_nice(state.weight))
+}
+
+fn task_info_idle(cpu: CpuId) -> TaskInfo {
+    (format!("swapper/{cpu}"), 0, 120)
+}
+
+fn task_fields(ctx: &KernelCtx, task: Option<TaskId>) -> String {
+    let (comm, pid, prio) = task_info(ctx, task, 0);
+    format!("comm={comm} pid={pid} prio={prio}")
+}
@@ -0,0 +1,220 @@
+use std::{fmt::Write, ops::Range};
+
+use rustc_hash::FxHashMap;
+
+use crate::core::{CpuId, KernelCtx, SchedCoreEvent, TaskId, TaskState, Ticks, weight_to_nice};
+
+const SYMBOLS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+// ANSI foreground colors, cycled through alongside the symbols
+const COLORS: [u8; 6] = [31, 32, 33, 34, 35, 36];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GanttKey {
+    // A symbol per task, in order of first running
+    Task,
+    // A symbol per distinct weight, heaviest first
+    Weight,
+}
+
+// Per-CPU timeline of which task ran at each tick, built from CpuCurrentChange
+// events. Idle ticks are '.', or '!' while runnable tasks are waiting.
+pub struct Gantt {
+    pub key: GanttKey,
+    // Color symbols with ANSI escapes
+    pub color: bool,
+    // Finished runs on each CPU, as (start, end, task), in order
+    runs: Vec<Vec<(Ticks, Ticks, TaskId)>>,
+    open: Vec<Option<(TaskId, Ticks)>>,
+    // Name, weight and order of first running, kept past reaping
+    tasks: FxHashMap<TaskId, (String, u64, usize)>,
+    // Number of runnable tasks waiting for a CPU, from each tick on
+    waiting: Vec<(Ticks, usize)>,
+    end: Ticks,
+}
+
+impl Gantt {
+    pub fn new(key: GanttKey) -> Self {
+        Self {
+            key,
+            color: false,
+            runs: Vec::new(),
+            open: Vec::new(),
+            tasks: FxHashMap::default(),
+            waiting: Vec::new(),
+            end: 0,
+        }
+    }
+
+    pub fn record(&mut self, events: &[(Ticks, SchedCoreEvent)], ctx: &KernelCtx) {
+        self.runs.resize(ctx.cpus.len(), Vec::new());
+        self.open.resize(ctx.cpus.len(), None);
+        let mut waiting = self.waiting.last().map_or(0, |&(_, n)| n);
+
+        for &(at, ref event) in events {
+            self.end = self.end.max(at);
+            match *event {
+                SchedCoreEvent::CpuCurrentChange { cpu, from, to } => {
+                    if from.is_some()
+                        && let Some((task, start)) = self.open[cpu].take()
+                    {
+                        self.runs[cpu].push((start, at, task));
+                    }
+                    if let Some(task) = to {
+                        self.open[cpu] = Some((task, at));
+                        let order = self.tasks.len();
+                        self.tasks.entry(task).or_insert_with(|| {
+                            let state = ctx.task(task);
+                            (state.attrs.name.clone(), state.weight, order)
+                        });
+                    }
+                }
+                SchedCoreEvent::TaskWakeup { .. } => waiting += 1,
+                SchedCoreEvent::TaskStateChange { from, to, .. } => {
+                    if to == TaskState::Runnable {
+                        waiting += 1;
+                    }
+                    if from == TaskState::Runnable {
+                        waiting = waiting.saturating_sub(1);
+                    }
+                }
+                _ => continue,
+            }
+            match self.waiting.last_mut() {
+                Some(last) if last.0 == at => last.1 = waiting,
+                _ => self.waiting.push((at, waiting)),
+            }
+        }
+        self.end = self.end.max(ctx.now);
+    }
+
+    // `window` squeezed into at most `width` columns. Wider windows are
+    // summarized, each column showing what occupied most of its ticks.
+    pub fn render(&self, window: Range<Ticks>, width: usize) -> String {
+        assert!(width > 0, "Gantt width must be nonzero");
+        let start = window.start;
+        let end = window.end.min(self.end).max(start);
+        let per = (end - start).div_ceil(width as u64).max(1);
+        let symbols = self.symbols();
+
+        let mut out = String::new();
+        let _ = writeln!(out, "t={start}..{end}, {per} tick(s) per column");
+        let mut shown: Vec<TaskId> = Vec::new();
+        for cpu in 0..self.runs.len() {
+            let _ = write!(out, "CPU {cpu:<3} |");
+            let mut col = start;
+            while col < end {
+                let bucket = col..(col + per).min(end);
+                let mut counts: FxHashMap<Option<TaskId>, u64> = FxHashMap::default();
+                let mut starved = false;
+                for t in bucket.clone() {
+                    let task = self.occupant(cpu, t);
+                    *counts.entry(task).or_default() += 1;
+                    starved |= task.is_none() && self.waiting_at(t) > 0;
+                }
+                // Ties go to the lowest TaskId, with idle last, so output is stable
+                let top = counts
+                    .into_iter()
+                    .max_by_key(|&(task, n)| (n, task.map(|t| usize::MAX - t)))
+                    .and_then(|(task, _)| task);
+                match top {
+                    Some(task) => {
+                        let (symbol, color) = symbols[&task];
+                        if self.color {
+                            let _ = write!(out, "\x1b[{color}m{symbol}\x1b[0m");
+                        } else {
+                            out.push(symbol);
+                        }
+                        if !shown.contains(&task) {
+                            shown.push(task);
+                        }
+                    }
+                    None => out.push(if starved { '!' } else { '.' }),
+                }
+                col = bucket.end;
+            }
+            out.push_str("|\n");
+        }
+
+        self.write_legend(&mut out, &symbols, shown);
+        out
+    }
+
+    // The whole run, one tick per column, in pages of `width` ticks
+    pub fn pages(&self, width: usize) -> Vec<String> {
+        assert!(width > 0, "Gantt width must be nonzero");
+        (0..self.end)
+            .step_by(width)
+            .map(|start| self.render(start..start + width as u64, width))
+            .collect()
+    }
+
+    fn occupant(&self, cpu: CpuId, t: Ticks) -> Option<TaskId> {
+        let runs = &self.runs[cpu];
+        let i = runs.partition_point(|&(_, end, _)| end <= t);
+        if let Some(&(start, _, task)) = runs.get(i)
+            && start <= t
+        {
+            return Some(task);
+        }
+        self.open[cpu]
+            .filter(|&(_, start)| start <= t)
+            .map(|(task, _)| task)
+    }
+
+    fn waiting_at(&self, t: Ticks) -> usize {
+        let i = self.waiting.partition_point(|&(at, _)| at <= t);
+        i.checked_sub(1).map_or(0, |i| self.waiting[i].1)
+    }
+
+    // Symbol and color of every task seen
+    fn symbols(&self) -> FxHashMap<TaskId, (char, u8)> {
+        let mut weights: Vec<u64> = self.tasks.values().map(|&(_, weight, _)| weight).collect();
+        weights.sort_unstable_by(|a, b| b.cmp(a));
+        weights.dedup();
+
+        self.tasks
+            .iter()
+            .map(|(&task, &(_, weight, order))| {
+                let index = match self.key {
+                    GanttKey::Task => order,
+                    GanttKey::Weight => weights.binary_search_by(|w| weight.cmp(w)).unwrap(),
+                };
+                let symbol = SYMBOLS[index % SYMBOLS.len()] as char;
+                (task, (symbol, COLORS[index % COLORS.len()]))
+            })
+            .collect()
+    }
+
+    fn write_legend(
+        &self,
+        out: &mut String,
+        symbols: &FxHashMap<TaskId, (char, u8)>,
+        mut shown: Vec<TaskId>,
+    ) {
+        match self.key {
+            GanttKey::Task => shown.sort_by_key(|task| self.tasks[task].2),
+            GanttKey::Weight => shown.sort_by_key(|task| std::cmp::Reverse(self.tasks[task].1)),
+        }
+        let mut entries: Vec<String> = Vec::new();
+        for task in shown {
+            let (name, weight, _) = &self.tasks[&task];
+            let symbol = symbols[&task].0;
+            let entry = match self.key {
+                GanttKey::Task if name.is_empty() => format!("{symbol}=task {task}"),
+                GanttKey::Task => format!("{symbol}={name}"),
+                GanttKey::Weight => {
+                    format!(
+                        "{symbol}=weight {weight} (nice {})",
+                        weight_to_nice(*weight)
+                    )
+                }
+            };
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        entries.push(".=idle".to_string());
+        entries.push("!=idle while tasks wait".to_string());
+        let _ = writeln!(out, "{}", entries.join("  "));
+    }
+}
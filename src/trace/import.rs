@@ -0,0 +1,510 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    core::{
+        Ticks,
+        prio::{MAX_NICE, MIN_NICE},
+    },
+    sim::Job,
+};
+
+// Scheduler events read from `trace-cmd report` or `perf script` text. Every
+// run burst of a task (wakeup to sleep or exit) becomes a Job, with the CPU
+// time it used as run_time, arriving at its wakeup time. A burst woken by
+// another traced task names the waker's burst as its waker, so it is placed as
+// that wakeup would be when the waker is still running.
+#[derive(Debug)]
+pub struct TraceImport {
+    // Sorted on arrival_time, relative to the first event
+    pub jobs: Vec<Job>,
+    // From the trace-cmd "cpus=" header, or the highest CPU seen
+    pub num_cpus: usize,
+    pub events: usize,
+    // Lines that are not scheduler events
+    pub skipped: usize,
+}
+
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    Parse { line: usize, msg: String },
+}
+
+type Pid = u32;
+type Nanos = u64;
+
+#[derive(Default)]
+struct TaskTrace {
+    comm: String,
+    prio: Option<i32>,
+    // Start of the current burst, and CPU time used in it so far
+    burst_start: Option<Nanos>,
+    run: Nanos,
+    running_since: Option<Nanos>,
+    // Bursts ended so far, numbering the current one
+    bursts: u64,
+    // (pid, burst) of the task that woke the current burst
+    waker: Option<(Pid, u64)>,
+}
+
+struct Burst {
+    pid: Pid,
+    seq: u64,
+    comm: String,
+    prio: Option<i32>,
+    start: Nanos,
+    run: Nanos,
+    waker: Option<(Pid, u64)>,
+}
+
+enum Event {
+    Switch {
+        prev_pid: Pid,
+        prev_comm: String,
+        prev_prio: Option<i32>,
+        // Still runnable, rather than sleeping or exiting
+        prev_runnable: bool,
+        next_pid: Pid,
+        next_comm: String,
+        next_prio: Option<i32>,
+    },
+    Wakeup {
+        pid: Pid,
+        comm: String,
+        prio: Option<i32>,
+    },
+    Fork {
+        child_pid: Pid,
+        child_comm: String,
+    },
+    Exit {
+        pid: Pid,
+    },
+}
+
+struct Record {
+    cpu: usize,
+    ts: Nanos,
+    // The task running on the CPU, which the line is attributed to
+    owner: Option<Pid>,
+    event: Event,
+}
+
+const EVENTS: [&str; 5] = [
+    "sched_switch",
+    "sched_wakeup",
+    "sched_wakeup_new",
+    "sched_process_fork",
+    "sched_process_exit",
+];
+
+impl TraceImport {
+    pub fn from_file(path: impl AsRef<Path>, tick_ns: u64) -> Result<Self, TraceError> {
+        Self::parse(&fs::read_to_string(path)?, tick_ns)
+    }
+
+    pub fn parse(text: &str, tick_ns: u64) -> Result<Self, TraceError> {
+        assert!(tick_ns > 0, "Tick length must be nonzero");
+        let mut tasks: FxHashMap<Pid, TaskTrace> = FxHashMap::default();
+        let mut bursts = Vec::new();
+        let mut header_cpus = None;
+        let mut max_cpu = 0;
+        let mut first = None;
+        let mut last = 0;
+        let mut events = 0;
+        let mut skipped = 0;
+
+        for (index, line) in text.lines().enumerate() {
+            let err = |msg: String| TraceError::Parse {
+                line: index + 1,
+                msg,
+            };
+            if let Some(cpus) = line.trim().strip_prefix("cpus=") {
+                header_cpus = Some(
+                    cpus.parse()
+                        .map_err(|_| err(format!("bad CPU count '{cpus}'")))?,
+                );
+                continue;
+            }
+            let Some(Record {
+                cpu,
+                ts,
+                owner,
+                event,
+            }) = parse_line(line).map_err(err)?
+            else {
+                skipped += 1;
+                continue;
+            };
+            events += 1;
+            max_cpu = max_cpu.max(cpu);
+            first.get_or_insert(ts);
+            if ts < last {
+                return Err(err("timestamps go backwards".to_string()));
+            }
+            last = ts;
+
+            match event {
+                Event::Switch {
+                    prev_pid,
+                    prev_comm,
+                    prev_prio,
+                    prev_runnable,
+                    next_pid,
+                    next_comm,
+                    next_prio,
+                } => {
+                    if prev_pid != 0 {
+                        let task = tasks.entry(prev_pid).or_default();
+                        task.comm = prev_comm;
+                        task.prio = prev_prio.or(task.prio);
+                        if let Some(since) = task.running_since.take() {
+                            task.run += ts - since;
+                        }
+                        if !prev_runnable {
+                            bursts.extend(end_burst(prev_pid, task));
+                        }
+                    }
+                    if next_pid != 0 {
+                        let task = tasks.entry(next_pid).or_default();
+                        task.comm = next_comm;
+                        task.prio = next_prio.or(task.prio);
+                        // Already runnable when the trace started, or its wakeup was lost
+                        task.burst_start.get_or_insert(ts);
+                        task.running_since = Some(ts);
+                    }
+                }
+                Event::Wakeup { pid, comm, prio } => {
+                    // Woken by whichever task was running on the CPU that
+                    // reported the wakeup
+                    let waker =
+                        owner
+                            .filter(|&owner| owner != 0 && owner != pid)
+                            .and_then(|owner| {
+                                let waker = tasks.get(&owner)?;
+                                waker.running_since.map(|_| (owner, waker.bursts))
+                            });
+                    let task = tasks.entry(pid).or_default();
+                    task.comm = comm;
+                    task.prio = prio.or(task.prio);
+                    if task.burst_start.is_none() {
+                        task.burst_start = Some(ts);
+                        task.waker = waker;
+                    }
+                }
+                Event::Fork {
+                    child_pid,
+                    child_comm,
+                } => {
+                    let task = tasks.entry(child_pid).or_default();
+                    task.comm = child_comm;
+                    task.burst_start.get_or_insert(ts);
+                }
+                // Accounted for by the switch away from the exiting task
+                Event::Exit { pid } => {
+                    tasks.entry(pid).or_default();
+                }
+            }
+        }
+
+        // Bursts still open when the trace ends are cut short
+        let mut open: Vec<_> = tasks.into_iter().collect();
+        open.sort_by_key(|(pid, _)| *pid);
+        for (pid, mut task) in open {
+            if let Some(since) = task.running_since.take() {
+                task.run += last - since;
+            }
+            bursts.extend(end_burst(pid, &mut task));
+        }
+
+        let t0 = first.unwrap_or(0);
+        bursts.sort_by_key(|burst| (burst.start, burst.pid));
+        let ids: FxHashMap<(Pid, u64), u64> = bursts
+            .iter()
+            .enumerate()
+            .map(|(id, burst)| ((burst.pid, burst.seq), id as u64))
+            .collect();
+        let jobs = bursts
+            .into_iter()
+            .enumerate()
+            .map(|(id, burst)| {
+                let mut tags = vec![format!("pid={}", burst.pid)];
+                // Realtime and deadline tasks are approximated by the heaviest nice level
+                let nice = burst.prio.map(|prio| {
+                    if prio < 100 {
+                        tags.push("rt".to_string());
+                    }
+                    prio.saturating_sub(120).clamp(MIN_NICE, MAX_NICE)
+                });
+                Job {
+                    id: id as u64,
+                    arrival_time: (burst.start - t0) / tick_ns,
+                    run_time: burst.run.div_ceil(tick_ns).max(1) as Ticks,
+                    nice,
+                    name: burst.comm,
+                    tags,
+                    waker: burst.waker.and_then(|waker| ids.get(&waker).copied()),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            jobs,
+            num_cpus: header_cpus.unwrap_or(max_cpu + 1),
+            events,
+            skipped,
+        })
+    }
+}
+
+fn end_burst(pid: Pid, task: &mut TaskTrace) -> Option<Burst> {
+    let start = task.burst_start.take()?;
+    let run = std::mem::take(&mut task.run);
+    let waker = task.waker.take();
+    let seq = task.bursts;
+    task.bursts += 1;
+    (run > 0).then(|| Burst {
+        pid,
+        seq,
+        comm: task.comm.clone(),
+        prio: task.prio,
+        start,
+        run,
+        waker,
+    })
+}
+
+// Scheduler event lines, None for anything else
+fn parse_line(line: &str) -> Result<Option<Record>, String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let Some(pos) = tokens.iter().position(|token| {
+        token
+            .strip_suffix(':')
+            .map(|name| name.strip_prefix("sched:").unwrap_or(name))
+            .is_some_and(|name| EVENTS.contains(&name))
+    }) else {
+        return Ok(None);
+    };
+    let token = tokens[pos];
+    let name = token.trim_end_matches(':');
+    let name = name.strip_prefix("sched:").unwrap_or(name);
+
+    let ts = tokens[..pos]
+        .iter()
+        .rev()
+        .find_map(|token| token.strip_suffix(':').and_then(parse_timestamp))
+        .ok_or_else(|| format!("{name}: missing timestamp"))?
+        .map_err(|msg| format!("{name}: {msg}"))?;
+    let (cpu_pos, cpu) = tokens[..pos]
+        .iter()
+        .enumerate()
+        .find_map(|(i, token)| {
+            token
+                .strip_prefix('[')
+                .and_then(|token| token.strip_suffix(']'))
+                .and_then(|cpu| cpu.parse().ok())
+                .map(|cpu| (i, cpu))
+        })
+        .ok_or_else(|| format!("{name}: missing CPU"))?;
+    // "comm-pid" from trace-cmd, or "comm pid" from perf
+    let owner = cpu_pos.checked_sub(1).map(|i| tokens[i]).and_then(|token| {
+        token
+            .rsplit_once('-')
+            .map_or(token, |(_, pid)| pid)
+            .parse()
+            .ok()
+    });
+
+    let offset = line.find(token).expect("token comes from line") + token.len();
+    let fields = line[offset..].trim();
+    let event = match name {
+        "sched_switch" => parse_switch(fields),
+        "sched_wakeup" | "sched_wakeup_new" => parse_wakeup(fields),
+        "sched_process_fork" => {
+            let fields = Fields::new(fields);
+            Ok(Event::Fork {
+                child_pid: fields.pid("child_pid")?,
+                child_comm: fields.get("child_comm")?.to_string(),
+            })
+        }
+        "sched_process_exit" => Ok(Event::Exit {
+            pid: Fields::new(fields).pid("pid")?,
+        }),
+        _ => unreachable!("event list"),
+    }
+    .map_err(|msg| format!("{name}: {msg}"))?;
+
+    Ok(Some(Record {
+        cpu,
+        ts,
+        owner,
+        event,
+    }))
+}
+
+// Seconds with up to nanosecond precision, such as "1234.567890". None when
+// `token` is not a timestamp, and an error when it is out of range.
+fn parse_timestamp(token: &str) -> Option<Result<Nanos, String>> {
+    let (secs, frac) = token.split_once('.')?;
+    if frac.is_empty() || frac.len() > 9 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let secs: u64 = secs.parse().ok()?;
+    let frac: u64 = format!("{frac:0<9}").parse().ok()?;
+    Some(
+        secs.checked_mul(1_000_000_000)
+            .and_then(|ns| ns.checked_add(frac))
+            .ok_or_else(|| format!("timestamp '{token}' out of range")),
+    )
+}
+
+fn parse_switch(fields: &str) -> Result<Event, String> {
+    let (prev, next) = fields
+        .split_once("==>")
+        .ok_or_else(|| "missing '==>'".to_string())?;
+
+    // Either key=value fields, or perf's "comm:pid [prio] state ==> comm:pid [prio]"
+    if fields.contains("prev_pid=") {
+        let prev = Fields::new(prev);
+        let next = Fields::new(next);
+        return Ok(Event::Switch {
+            prev_pid: prev.pid("prev_pid")?,
+            prev_comm: prev.get("prev_comm")?.to_string(),
+            prev_prio: prev.prio("prev_prio")?,
+            prev_runnable: prev.get("prev_state")?.starts_with('R'),
+            next_pid: next.pid("next_pid")?,
+            next_comm: next.get("next_comm")?.to_string(),
+            next_prio: next.prio("next_prio")?,
+        });
+    }
+
+    let (prev, state) = prev
+        .trim()
+        .rsplit_once(' ')
+        .ok_or_else(|| "missing prev state".to_string())?;
+    let (prev_comm, prev_pid, prev_prio) = parse_task(prev)?;
+    let (next_comm, next_pid, next_prio) = parse_task(next)?;
+    Ok(Event::Switch {
+        prev_pid,
+        prev_comm,
+        prev_prio,
+        prev_runnable: state.starts_with('R'),
+        next_pid,
+        next_comm,
+        next_prio,
+    })
+}
+
+fn parse_wakeup(fields: &str) -> Result<Event, String> {
+    if fields.contains("pid=") {
+        let fields = Fields::new(fields);
+        return Ok(Event::Wakeup {
+            pid: fields.pid("pid")?,
+            comm: fields.get("comm")?.to_string(),
+            prio: fields.prio("prio")?,
+        });
+    }
+
+    // perf's "comm:pid [prio] CPU:002", possibly with "success=1"
+    let task = fields
+        .split(" CPU:")
+        .next()
+        .unwrap_or(fields)
+        .trim_end()
+        .trim_end_matches("success=1")
+        .trim_end();
+    let (comm, pid, prio) = parse_task(task)?;
+    Ok(Event::Wakeup { pid, comm, prio })
+}
+
+// "comm:pid [prio]"; comm may itself contain spaces and colons
+fn parse_task(task: &str) -> Result<(String, Pid, Option<i32>), String> {
+    let task = task.trim();
+    let (task, prio) = match task.rsplit_once(' ') {
+        Some((rest, prio)) if prio.starts_with('[') && prio.ends_with(']') => {
+            let prio = prio[1..prio.len() - 1]
+                .parse()
+                .map_err(|_| format!("bad priority '{prio}'"))?;
+            (rest, Some(prio))
+        }
+        _ => (task, None),
+    };
+    let (comm, pid) = task
+        .rsplit_once(':')
+        .ok_or_else(|| format!("bad task '{task}'"))?;
+    let pid = pid.parse().map_err(|_| format!("bad pid '{pid}'"))?;
+    Ok((comm.to_string(), pid, prio))
+}
+
+// key=value pairs; values run up to the next key, so comms may contain spaces
+struct Fields<'a> {
+    pairs: Vec<(&'a str, String)>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut pairs: Vec<(&'a str, String)> = Vec::new();
+        for token in text.split_whitespace() {
+            match token.split_once('=') {
+                Some((key, value)) if !key.is_empty() => pairs.push((key, value.to_string())),
+                _ => {
+                    if let Some((_, value)) = pairs.last_mut() {
+                        value.push(' ');
+                        value.push_str(token);
+                    }
+                }
+            }
+        }
+        Self { pairs }
+    }
+
+    fn get(&self, key: &str) -> Result<&str, String> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value.as_str())
+            .ok_or_else(|| format!("missing {key}"))
+    }
+
+    fn pid(&self, key: &str) -> Result<Pid, String> {
+        let value = self.get(key)?;
+        value.parse().map_err(|_| format!("bad {key} '{value}'"))
+    }
+
+    fn prio(&self, key: &str) -> Result<Option<i32>, String> {
+        match self.get(key) {
+            Ok(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("bad {key} '{value}'")),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read trace: {err}"),
+            Self::Parse { line, msg } => write!(f, "line {line}: {msg}"),
+        }
+    }
+}
+
+impl Error for TraceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for TraceError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
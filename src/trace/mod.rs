@@ -0,0 +1,15 @@
+// Conversion between runs and kernel trace formats. Wherever it appears,
+// `tick_ns` is the length of a tick in trace time, in nanoseconds.
+//
+// The exporters (FtraceWriter, ChromeTrace and Gantt) take the events of each
+// Sim::step_timed or SchedCore::tick_timed call, together with the KernelCtx,
+// before the next step, so that completed tasks have not been reaped yet.
+pub mod chrome;
+pub mod ftrace;
+pub mod gantt;
+pub mod import;
+
+pub use chrome::ChromeTrace;
+pub use ftrace::FtraceWriter;
+pub use gantt::{Gantt, GanttKey};
+pub use import::{TraceError, TraceImport};
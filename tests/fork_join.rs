@@ -0,0 +1,72 @@
+use rustc_hash::FxHashMap;
+use scx_model::{
+    Job, SchedCoreEvent, Sim,
+    core::{BlockReason, TaskId, TaskState},
+    scheduler::FifoScheduler,
+    sim::ForkJoin,
+};
+
+const CHILDREN: u64 = 4;
+const INTERVAL: u64 = 4;
+
+#[test]
+fn barrier_children_release_together() {
+    let job = Job {
+        id: 0,
+        arrival_time: 0,
+        run_time: 6,
+        name: "parent".to_string(),
+        fork: Some(ForkJoin {
+            at: 2,
+            children: CHILDREN,
+            child_run_time: 3 * INTERVAL,
+            barrier_interval: Some(INTERVAL),
+        }),
+        ..Default::default()
+    };
+    // Fewer CPUs than children, so some reach each barrier later than others
+    let mut sim = Sim::<FifoScheduler>::new(vec![job], 3);
+
+    let mut parent = None;
+    // Child --> times it blocked at a barrier, and times it was woken after forking
+    let mut blocked: FxHashMap<TaskId, Vec<u64>> = FxHashMap::default();
+    let mut woken: FxHashMap<TaskId, Vec<u64>> = FxHashMap::default();
+    while !sim.all_jobs_completed() {
+        assert!(sim.core.now() < 1000, "job did not complete");
+        for (at, event) in sim.step_timed() {
+            match event {
+                SchedCoreEvent::TaskWakeup { task, .. } => {
+                    let parent = *parent.get_or_insert(task);
+                    if task != parent {
+                        woken.entry(task).or_default().push(at);
+                    }
+                }
+                SchedCoreEvent::TaskStateChange {
+                    task,
+                    to: TaskState::Blocked,
+                    reason: Some(BlockReason::BlockAt),
+                    ..
+                } if Some(task) != parent => blocked.entry(task).or_default().push(at),
+                _ => {}
+            }
+        }
+    }
+
+    assert_eq!(blocked.len() as u64, CHILDREN);
+    // Two barriers before the children finish; the first wakeup is the fork
+    for barrier in 0..2 {
+        let last_arrival = blocked.values().map(|times| times[barrier]).max().unwrap();
+        let releases: Vec<u64> = woken.values().map(|times| times[barrier + 1]).collect();
+        assert!(
+            releases.iter().all(|&at| at == releases[0]),
+            "barrier {barrier} released children at {releases:?}"
+        );
+        assert!(releases[0] >= last_arrival);
+    }
+    assert!(
+        blocked
+            .values()
+            .any(|times| times[0] != blocked.values().next().unwrap()[0]),
+        "children should reach the first barrier at different times"
+    );
+}
@@ -0,0 +1,178 @@
+use scx_model::{
+    Job, SchedCoreEvent, Sim,
+    core::{CpuId, KernelCtx, TaskId, driver::SchedCore},
+    scheduler::{
+        EnqueueFlags, LayeredScheduler, SCX_SLICE_DFL, Scheduler, SelectCpuDecision, WakeFlags,
+        layered::{LayerKind, LayerMatch, LayerSpec, LayeredConfig},
+    },
+    sim::SimEvent,
+};
+
+// Queues every task on CPU 1, online or not
+struct PinToCpu1<const HOTPLUG_AWARE: bool>;
+
+impl<const HOTPLUG_AWARE: bool> Scheduler for PinToCpu1<HOTPLUG_AWARE> {
+    const HOTPLUG_AWARE: bool = HOTPLUG_AWARE;
+
+    fn init(_ctx: &mut KernelCtx) -> Self {
+        Self
+    }
+
+    fn select_cpu(
+        &mut self,
+        _ctx: &mut KernelCtx,
+        _task: TaskId,
+        _wakeup_cpu: CpuId,
+        _wake_flags: WakeFlags,
+    ) -> SelectCpuDecision {
+        SelectCpuDecision::DirectDispatch(1, SCX_SLICE_DFL)
+    }
+
+    fn enqueue(
+        &mut self,
+        ctx: &mut KernelCtx,
+        task: TaskId,
+        _flags: EnqueueFlags,
+        _prev_cpu: CpuId,
+    ) {
+        ctx.dsq_push_fifo(ctx.per_cpu_dsq(1), task, SCX_SLICE_DFL);
+    }
+
+    fn dispatch(&mut self, _ctx: &mut KernelCtx, _cpu: CpuId) {}
+}
+
+fn job(id: u64, name: &str, arrival_time: u64, run_time: u64) -> Job {
+    Job {
+        id,
+        arrival_time,
+        run_time,
+        name: name.to_string(),
+        ..Default::default()
+    }
+}
+
+fn run<S: Scheduler>(sim: &mut Sim<S>, max_ticks: u64) -> Vec<(u64, SchedCoreEvent)> {
+    let mut events = Vec::new();
+    while !sim.all_jobs_completed() {
+        assert!(sim.core.now() < max_ticks, "jobs did not complete");
+        events.extend(sim.step_timed());
+    }
+    events
+}
+
+// Ticks at which `cpu` started running a task
+fn switches_in(events: &[(u64, SchedCoreEvent)], cpu: CpuId) -> Vec<u64> {
+    events
+        .iter()
+        .filter_map(|(at, event)| match *event {
+            SchedCoreEvent::CpuCurrentChange {
+                cpu: c,
+                to: Some(_),
+                ..
+            } if c == cpu => Some(*at),
+            _ => None,
+        })
+        .collect()
+}
+
+// Four jobs queued on CPU 1, which goes offline at tick 2
+fn pinned_sim<S: Scheduler>(late: Vec<Job>) -> Sim<S> {
+    let jobs = (0..4).map(|id| job(id, "job", 0, 4)).chain(late).collect();
+    let mut sim = Sim::new(jobs, 2);
+    sim.schedule(2, SimEvent::CpuOffline { cpu: 1 });
+    sim
+}
+
+#[test]
+fn offline_moves_running_and_queued_tasks() {
+    let mut sim = pinned_sim::<PinToCpu1<true>>(Vec::new());
+    sim.step();
+    sim.step();
+    // One task running on CPU 1 and the rest queued behind it
+    assert!(sim.core.ctx.cpus[1].current.is_some());
+    assert_eq!(sim.core.ctx.dsq_nr_queued(sim.core.ctx.per_cpu_dsq(1)), 3);
+
+    let events = run(&mut sim, 100);
+    assert_eq!(sim.core.exit_reason(), None);
+    assert_eq!(sim.jobs.len(), 4);
+    assert!(switches_in(&events, 1).is_empty());
+    assert!(!switches_in(&events, 0).is_empty());
+}
+
+#[test]
+fn online_cpu_runs_tasks_again() {
+    let mut sim = pinned_sim::<PinToCpu1<true>>((4..8).map(|id| job(id, "late", 10, 4)).collect());
+    sim.schedule(5, SimEvent::CpuOnline { cpu: 1 });
+
+    let events = run(&mut sim, 100);
+    assert_eq!(sim.core.exit_reason(), None);
+    assert_eq!(sim.jobs.len(), 8);
+    let cpu1 = switches_in(&events, 1);
+    assert!(cpu1.iter().all(|&at| !(2..5).contains(&at)));
+    assert!(cpu1.iter().any(|&at| at >= 10));
+}
+
+#[test]
+fn dispatch_to_offline_cpu_ejects_unaware_scheduler() {
+    let mut sim = pinned_sim::<PinToCpu1<false>>(Vec::new());
+    let events = run(&mut sim, 100);
+    assert_eq!(sim.core.exit_reason(), Some("dispatched to offline CPU 1"));
+    assert!(
+        events
+            .iter()
+            .any(|(_, event)| matches!(event, SchedCoreEvent::SchedulerEjected { .. }))
+    );
+    // The core falls back to the global DSQ, so everything still completes
+    assert_eq!(sim.jobs.len(), 4);
+}
+
+#[test]
+fn repeated_hotplug_events_are_ignored() {
+    let mut sim = pinned_sim::<PinToCpu1<true>>(Vec::new());
+    sim.schedule(3, SimEvent::CpuOffline { cpu: 1 });
+    sim.schedule(6, SimEvent::CpuOnline { cpu: 1 });
+    sim.schedule(7, SimEvent::CpuOnline { cpu: 1 });
+    run(&mut sim, 100);
+    assert_eq!(sim.core.exit_reason(), None);
+    assert!(sim.core.ctx.cpus[1].online);
+}
+
+#[test]
+fn layered_runs_stranded_layer_elsewhere() {
+    let config = LayeredConfig {
+        layers: vec![
+            LayerSpec {
+                name: "pinned".to_string(),
+                matches: vec![vec![LayerMatch::NamePrefix("pinned".to_string())]],
+                kind: LayerKind::Confined { cpus: vec![1] },
+                slice: 2,
+                preempt: false,
+            },
+            LayerSpec {
+                name: "rest".to_string(),
+                matches: vec![vec![]],
+                kind: LayerKind::Open,
+                slice: 2,
+                preempt: false,
+            },
+        ],
+    };
+    let jobs = vec![
+        job(0, "pinned", 0, 6),
+        job(1, "pinned", 0, 6),
+        job(2, "other", 0, 4),
+    ];
+    let core = SchedCore::with_scheduler(2, |ctx| LayeredScheduler::new(ctx, config).unwrap());
+    let mut sim = Sim::with_core(jobs, core);
+    sim.schedule(2, SimEvent::CpuOffline { cpu: 1 });
+    sim.schedule(30, SimEvent::CpuOnline { cpu: 1 });
+
+    run(&mut sim, 100);
+    assert_eq!(sim.core.exit_reason(), None);
+    // Both pinned jobs finished on CPU 0 while CPU 1 was offline
+    assert!(
+        sim.jobs
+            .iter()
+            .all(|job| job.completion_time.is_some_and(|t| t < 30))
+    );
+}
@@ -0,0 +1,154 @@
+use scx_model::{
+    Job, Sim,
+    core::driver::SchedCore,
+    scheduler::FifoScheduler,
+    trace::{FtraceWriter, TraceError, TraceImport},
+};
+
+const TRACE_CMD: &str = "\
+cpus=2
+          <idle>-0     [000]     1.000000: sched_wakeup: comm=my-app pid=100 prio=110 target_cpu=000
+          <idle>-0     [000]     1.000000: sched_switch: prev_comm=swapper/0 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=my-app next_pid=100 next_prio=110
+          my-app-100   [000]     1.002000: sched_wakeup: comm=Web Content pid=200 prio=120 target_cpu=001
+          <idle>-0     [001]     1.002000: sched_switch: prev_comm=swapper/1 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=Web Content next_pid=200 next_prio=120
+          my-app-100   [000]     1.003000: irq_handler_entry: irq=1 name=timer
+          my-app-100   [000]     1.004000: sched_switch: prev_comm=my-app prev_pid=100 prev_prio=110 prev_state=R+ ==> next_comm=swapper/0 next_pid=0 next_prio=120
+          <idle>-0     [000]     1.005000: sched_switch: prev_comm=swapper/0 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=my-app next_pid=100 next_prio=110
+     Web Content-200   [001]     1.007000: sched_switch: prev_comm=Web Content prev_pid=200 prev_prio=120 prev_state=S ==> next_comm=swapper/1 next_pid=0 next_prio=120
+          my-app-100   [000]     1.010000: sched_switch: prev_comm=my-app prev_pid=100 prev_prio=110 prev_state=S ==> next_comm=swapper/0 next_pid=0 next_prio=120
+";
+
+const PERF: &str = "\
+          my:app   100 [000]     1.000000:       sched:sched_wakeup: my:app:100 [110] success=1 CPU:000
+         swapper     0 [000]     1.000000:       sched:sched_switch: swapper/0:0 [120] R ==> my:app:100 [110]
+          my:app   100 [000]     1.003000:       sched:sched_wakeup: worker:200 [120] CPU:001
+         swapper     0 [001]     1.003000:       sched:sched_switch: swapper/1:0 [120] R ==> worker:200 [120]
+          my:app   100 [000]     1.004000:       sched:sched_switch: my:app:100 [110] S ==> swapper/0:0 [120]
+          worker   200 [001]     1.006000:       sched:sched_switch: worker:200 S ==> swapper/1:0 [120]
+";
+
+// (name, arrival_time, run_time, nice, waker) of a job
+type Summary<'a> = (&'a str, u64, u64, Option<i32>, Option<u64>);
+
+fn summary(jobs: &[Job]) -> Vec<Summary<'_>> {
+    jobs.iter()
+        .map(|job| {
+            (
+                job.name.as_str(),
+                job.arrival_time,
+                job.run_time,
+                job.nice,
+                job.waker,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn trace_cmd_bursts_become_jobs() {
+    let trace = TraceImport::parse(TRACE_CMD, 1_000_000).unwrap();
+    assert_eq!(trace.num_cpus, 2);
+    assert_eq!(trace.skipped, 1);
+    // my-app is still runnable at the R+ switch, so its burst runs on
+    assert_eq!(
+        summary(&trace.jobs),
+        vec![
+            ("my-app", 0, 9, Some(-10), None),
+            ("Web Content", 2, 5, Some(0), Some(0)),
+        ]
+    );
+    assert_eq!(trace.jobs[1].tags, vec!["pid=200".to_string()]);
+}
+
+#[test]
+fn perf_bursts_become_jobs() {
+    let trace = TraceImport::parse(PERF, 1_000_000).unwrap();
+    assert_eq!(trace.num_cpus, 2);
+    // worker's prio is missing from its last switch, so it keeps the earlier one
+    assert_eq!(
+        summary(&trace.jobs),
+        vec![
+            ("my:app", 0, 4, Some(-10), None),
+            ("worker", 3, 3, Some(0), Some(0)),
+        ]
+    );
+}
+
+#[test]
+fn timestamps_going_backwards_are_rejected() {
+    let trace = "\
+          <idle>-0     [000]     2.000000: sched_wakeup: comm=a pid=1 prio=120 target_cpu=000
+          <idle>-0     [000]     1.000000: sched_wakeup: comm=b pid=2 prio=120 target_cpu=000
+";
+    match TraceImport::parse(trace, 1000) {
+        Err(TraceError::Parse { line: 2, .. }) => {}
+        other => panic!("expected a parse error on line 2, got {other:?}"),
+    }
+}
+
+#[test]
+fn extreme_priorities_are_clamped() {
+    let trace = "\
+          <idle>-0     [000]     1.000000: sched_switch: prev_comm=swapper/0 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=rt next_pid=1 next_prio=-2147483648
+              rt-1     [000]     1.001000: sched_switch: prev_comm=rt prev_pid=1 prev_prio=-2147483648 prev_state=S ==> next_comm=swapper/0 next_pid=0 next_prio=120
+";
+    let trace = TraceImport::parse(trace, 1000).unwrap();
+    assert_eq!(trace.jobs[0].nice, Some(-20));
+    assert!(trace.jobs[0].tags.contains(&"rt".to_string()));
+}
+
+#[test]
+fn ftrace_output_imports_as_the_same_jobs() {
+    let tick_ns = 1000;
+    let jobs = vec![
+        Job {
+            id: 0,
+            arrival_time: 0,
+            run_time: 30,
+            nice: Some(-5),
+            name: "first".to_string(),
+            ..Default::default()
+        },
+        Job {
+            id: 1,
+            arrival_time: 4,
+            run_time: 12,
+            nice: Some(3),
+            name: "second".to_string(),
+            ..Default::default()
+        },
+        Job {
+            id: 2,
+            arrival_time: 9,
+            run_time: 25,
+            name: "third".to_string(),
+            ..Default::default()
+        },
+    ];
+    let mut sim = Sim::with_core(jobs.clone(), SchedCore::<FifoScheduler>::new(2));
+    let mut writer = FtraceWriter::new(Vec::new(), tick_ns).unwrap();
+    while !sim.all_jobs_completed() {
+        assert!(sim.core.now() < 1000, "jobs did not complete");
+        let events = sim.step_timed();
+        writer.write(&events, &sim.core.ctx).unwrap();
+    }
+    let text = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+
+    let trace = TraceImport::parse(&text, tick_ns).unwrap();
+    let imported: Vec<_> = summary(&trace.jobs)
+        .into_iter()
+        .map(|(name, arrival, run, nice, _)| (name, arrival, run, nice))
+        .collect();
+    let expected: Vec<_> = jobs
+        .iter()
+        .map(|job| {
+            (
+                job.name.as_str(),
+                job.arrival_time,
+                job.run_time,
+                Some(job.nice.unwrap_or(0)),
+            )
+        })
+        .collect();
+    assert_eq!(imported, expected);
+}
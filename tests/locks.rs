@@ -0,0 +1,106 @@
+use scx_model::{
+    Job, SchedCoreEvent, Sim,
+    core::{CriticalSection, LockKind, driver::SchedCore, nice_to_weight},
+    scheduler::{PriqScheduler, SCX_SLICE_DFL},
+};
+
+const SECTION: u64 = 8;
+
+struct Inversion {
+    // The holder's weight while contended, and once it released the lock
+    boosted: u64,
+    restored: u64,
+    // The high priority waiter's lock_wait
+    lock_wait: u64,
+}
+
+// A nice 19 task takes a sleeping lock and runs out its slice, a nice -20
+// task then waits on the lock, and a nice 0 task competes with the holder for
+// the only CPU
+fn priority_inversion(priority_inheritance: bool) -> Inversion {
+    let mut core = SchedCore::<PriqScheduler>::new(1);
+    let lock = core.create_lock(LockKind::Sleep, priority_inheritance);
+    let job = |id, name: &str, arrival_time, run_time, nice, section: Option<u64>| Job {
+        id,
+        arrival_time,
+        run_time,
+        nice: Some(nice),
+        name: name.to_string(),
+        critical_sections: section
+            .map(|len| CriticalSection {
+                lock,
+                start: 0,
+                len,
+            })
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    };
+    let jobs = vec![
+        job(0, "low", 0, 10, 19, Some(SECTION)),
+        job(1, "medium", 1, 1000, 0, None),
+        job(2, "high", 1, 5, -20, Some(2)),
+    ];
+    let mut sim = Sim::with_core(jobs, core);
+
+    let mut holder = None;
+    let mut boosted = 0;
+    let mut restored = 0;
+    while !sim.all_jobs_completed() {
+        assert!(sim.core.now() < 10_000, "jobs did not complete");
+        for event in sim.step() {
+            match event {
+                SchedCoreEvent::LockContended { holder: task, .. } => holder = Some(task),
+                SchedCoreEvent::LockReleased { task, .. } if holder == Some(task) => {
+                    restored = sim.core.ctx.task(task).weight;
+                    holder = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(task) = holder {
+            boosted = boosted.max(sim.core.ctx.task(task).weight);
+        }
+    }
+
+    let high = sim
+        .jobs
+        .iter()
+        .find(|job| job.job.name == "high")
+        .expect("high job completed");
+    Inversion {
+        boosted,
+        restored,
+        lock_wait: high.lock_wait,
+    }
+}
+
+// Boosting does not undo the vtime the holder was charged for its first slice
+// at nice 19, so the medium task catches up on that before the holder runs its
+// critical section, at the waiter's weight
+fn wait_bound() -> u64 {
+    SCX_SLICE_DFL * nice_to_weight(0) / nice_to_weight(19) + SECTION + SCX_SLICE_DFL
+}
+
+#[test]
+fn priority_inheritance_boosts_lock_holder() {
+    let inversion = priority_inversion(true);
+    assert_eq!(inversion.boosted, nice_to_weight(-20));
+    assert_eq!(inversion.restored, nice_to_weight(19));
+    assert!(
+        inversion.lock_wait <= wait_bound(),
+        "high waited {} ticks",
+        inversion.lock_wait
+    );
+}
+
+#[test]
+fn without_priority_inheritance_the_holder_is_starved() {
+    let inversion = priority_inversion(false);
+    assert_eq!(inversion.boosted, nice_to_weight(19));
+    assert!(
+        inversion.lock_wait > 3 * wait_bound() / 2,
+        "high waited only {} ticks",
+        inversion.lock_wait
+    );
+}